@@ -27,6 +27,8 @@ fn main() -> io::Result<()> {
     process_directory(Path::new(css_dir), &mut output)?;
 
     println!("cargo:rerun-if-changed=static");
+    // Las migraciones se incluyen en el binario
+    println!("cargo:rerun-if-changed=migrations");
     Ok(())
 }
 
@@ -0,0 +1,7 @@
+CREATE TABLE items (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT ''
+);
+
+CREATE INDEX items_name_idx ON items (LOWER(name));
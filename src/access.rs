@@ -0,0 +1,164 @@
+// access.rs
+use crate::client_ip::ClientIp;
+use crate::config::{AccessConfig, AccessRule};
+use crate::error::AppError;
+use crate::paths;
+use crate::file_watch;
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::web::Data;
+use actix_web::{Error, HttpResponse, ResponseError};
+use ipnet::IpNet;
+use std::fs;
+use std::io;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+// Listas de acceso por red con recarga en caliente de los archivos. Si la recarga
+// falla se mantienen las listas anteriores.
+pub struct AccessControl {
+    config: RwLock<AccessConfig>,
+    rules: RwLock<Arc<Vec<CompiledRule>>>,
+}
+
+struct CompiledRule {
+    path_prefix: String,
+    allow: Vec<IpNet>,
+    deny: Vec<IpNet>,
+}
+
+impl AccessControl {
+    pub fn new(config: &AccessConfig) -> io::Result<Self> {
+        Ok(AccessControl {
+            rules: RwLock::new(Arc::new(compile(config)?)),
+            config: RwLock::new(config.clone()),
+        })
+    }
+
+    // Vuelve a leer los archivos de redes
+    pub fn reload(&self) -> io::Result<()> {
+        let config = self.config.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+        self.replace(&config)
+    }
+
+    // Aplica una configuración nueva (por ejemplo, tras recargar config.toml)
+    pub fn replace(&self, config: &AccessConfig) -> io::Result<()> {
+        let rules = compile(config)?;
+        info!("Listas de acceso recargadas: {} reglas", rules.len());
+        *self.rules.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::new(rules);
+        *self.config.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = config.clone();
+        Ok(())
+    }
+
+    // Archivos de redes a vigilar
+    pub fn watched_files(&self) -> Vec<PathBuf> {
+        let config = self.config.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        config
+            .rules
+            .iter()
+            .flat_map(|rule| rule.allow_file.iter().chain(rule.deny_file.iter()))
+            .map(PathBuf::from)
+            .collect()
+    }
+
+    // Denegada si alguna regla de la ruta la deniega o tiene lista de permitidas sin ella
+    pub fn allows(&self, ip: IpAddr, path: &str) -> bool {
+        let rules = self.rules.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+        rules
+            .iter()
+            .filter(|rule| paths::matches_prefix(path, &rule.path_prefix))
+            .all(|rule| {
+                !rule.deny.iter().any(|network| network.contains(&ip))
+                    && (rule.allow.is_empty() || rule.allow.iter().any(|network| network.contains(&ip)))
+            })
+    }
+
+    // Comprobación para los middlewares; None en sockets Unix, donde no hay IP
+    pub fn check(&self, req: &ServiceRequest) -> Option<HttpResponse> {
+        req.peer_addr()?;
+        let ClientIp(ip) = ClientIp::from_service_request(req);
+        let path = paths::route_path(req);
+        if self.allows(ip, path) {
+            return None;
+        }
+        warn!("Acceso denegado a {} ({})", ip, path);
+        Some(AppError::Forbidden("Acceso denegado".to_string()).error_response())
+    }
+}
+
+fn compile(config: &AccessConfig) -> io::Result<Vec<CompiledRule>> {
+    config.rules.iter().map(compile_rule).collect()
+}
+
+fn compile_rule(rule: &AccessRule) -> io::Result<CompiledRule> {
+    let mut allow = rule.allow.clone();
+    if let Some(path) = &rule.allow_file {
+        allow.extend(read_networks(path)?);
+    }
+    let mut deny = rule.deny.clone();
+    if let Some(path) = &rule.deny_file {
+        deny.extend(read_networks(path)?);
+    }
+
+    // Con allow_file, un archivo vacío no abre el acceso a todo el mundo
+    if allow.is_empty() && rule.allow_file.is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("La lista de redes permitidas para '{}' está vacía", rule.path_prefix),
+        ));
+    }
+
+    Ok(CompiledRule {
+        path_prefix: rule.path_prefix.clone(),
+        allow,
+        deny,
+    })
+}
+
+// Una red por línea ("10.0.0.0/8", "2001:db8::/32") o una IP suelta
+fn read_networks(path: &str) -> io::Result<Vec<IpNet>> {
+    let content = fs::read_to_string(path).map_err(|e| io::Error::new(e.kind(), format!("No se pudo leer '{}': {}", path, e)))?;
+    let mut networks = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let network = line
+            .parse::<IpNet>()
+            .or_else(|_| line.parse::<IpAddr>().map(IpNet::from))
+            .map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("{}:{}: red inválida '{}'", path, number + 1, line))
+            })?;
+        networks.push(network);
+    }
+    Ok(networks)
+}
+
+pub async fn access_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let Some(access) = req.app_data::<Data<AccessControl>>().cloned() else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+
+    match access.check(&req) {
+        Some(res) => Ok(req.into_response(res).map_into_right_body()),
+        None => Ok(next.call(req).await?.map_into_left_body()),
+    }
+}
+
+// Monitorear los archivos de redes y recargarlos al cambiar
+pub async fn monitor_lists(access: Arc<AccessControl>) -> io::Result<()> {
+    file_watch::watch_files("listas de acceso", &access.watched_files(), Duration::from_millis(200), || {
+        if let Err(e) = access.reload() {
+            error!("Error al recargar las listas de acceso (se mantienen las anteriores): {}", e);
+        }
+    })
+    .await
+}
@@ -0,0 +1,173 @@
+// admin.rs
+use crate::access::{self, AccessControl};
+use crate::client_ip::ClientIp;
+use crate::config::{AdminConfig, Config};
+use crate::connections::{self, ConnectionTracker};
+use crate::error::{self, AppError};
+use crate::handlers;
+use crate::health::{self, Health};
+use crate::logging::{self, RequestLogging};
+use crate::login::UserStore;
+use crate::metrics::{self, Metrics};
+use crate::mtls::{self, ClientIdentity, MtlsAuthorizer};
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderMap, HeaderValue};
+use actix_web::middleware::{self, Next};
+use actix_web::web::{self, Data};
+use actix_web::{App, Error, ResponseError};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ipnet::IpNet;
+use std::net::IpAddr;
+use std::sync::Arc;
+use tracing::warn;
+
+// Lista de IP permitidas, usuarios para autenticación básica y roles de
+// certificado de cliente que la sustituyen
+pub struct AdminAccess {
+    allowed_ips: Vec<IpNet>,
+    users: Option<Arc<UserStore>>,
+    client_cert_roles: Vec<String>,
+}
+
+impl AdminAccess {
+    pub fn new(config: &AdminConfig) -> Self {
+        AdminAccess {
+            allowed_ips: config.allowed_ips.clone(),
+            users: (!config.users.is_empty()).then(|| Arc::new(UserStore::new(&config.users))),
+            client_cert_roles: config.client_cert_roles.clone(),
+        }
+    }
+
+    pub fn allows_certificate(&self, identity: &ClientIdentity) -> bool {
+        self.client_cert_roles.iter().any(|role| identity.has_role(role))
+    }
+
+    pub fn allows_ip(&self, ip: IpAddr) -> bool {
+        self.allowed_ips.is_empty() || self.allowed_ips.iter().any(|net| net.contains(&ip))
+    }
+}
+
+// Estado de la aplicación de administración
+#[derive(Clone)]
+pub struct AdminState {
+    pub access: Data<AdminAccess>,
+    pub access_control: Data<AccessControl>,
+    pub metrics: Data<Metrics>,
+    pub connections: Data<ConnectionTracker>,
+    pub request_logging: Data<RequestLogging>,
+    pub health: Data<Health>,
+    pub mtls_authorizer: Data<MtlsAuthorizer>,
+}
+
+impl AdminState {
+    pub fn new(
+        config: &Config,
+        metrics: Data<Metrics>,
+        connections: Data<ConnectionTracker>,
+        health: Data<Health>,
+        access_control: Data<AccessControl>,
+    ) -> Self {
+        AdminState {
+            access: Data::new(AdminAccess::new(&config.admin)),
+            access_control,
+            metrics,
+            connections,
+            request_logging: Data::new(RequestLogging::new(&config.logging)),
+            health,
+            mtls_authorizer: Data::new(MtlsAuthorizer::new(&config.mtls)),
+        }
+    }
+}
+
+// Aplicación de los listeners de administración; no se expone en los listeners públicos
+pub fn build_admin_app(
+    state: &AdminState,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = Error,
+        InitError = (),
+    >,
+> {
+    App::new()
+        .app_data(state.access.clone())
+        .app_data(state.access_control.clone())
+        .app_data(state.metrics.clone())
+        .app_data(state.connections.clone())
+        .app_data(state.request_logging.clone())
+        .app_data(state.health.clone())
+        .app_data(state.mtls_authorizer.clone())
+        .wrap(middleware::from_fn(mtls::mtls_middleware))
+        .wrap(middleware::from_fn(admin_access_middleware))
+        .wrap(middleware::from_fn(access::access_middleware))
+        .wrap(error::error_handlers())
+        .wrap(middleware::from_fn(logging::request_logging_middleware))
+        .route("/metrics", web::get().to(metrics::export_metrics))
+        .route("/healthz", web::get().to(health::healthz))
+        .route("/readyz", web::get().to(health::readyz))
+        .route("/livez", web::get().to(health::livez))
+        .route("/connections", web::get().to(connections::connections_handler))
+        .default_service(web::route().to(handlers::not_found))
+}
+
+// "Authorization: Basic base64(usuario:contraseña)"
+fn basic_credentials(headers: &HeaderMap) -> Option<(String, String)> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, encoded) = value.split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("basic") {
+        return None;
+    }
+
+    let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+    let (username, password) = decoded.split_once(':')?;
+    Some((username.to_string(), password.to_string()))
+}
+
+pub async fn admin_access_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let Some(access) = req.app_data::<Data<AdminAccess>>().cloned() else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+
+    // En sockets Unix no hay IP: el acceso lo controlan los permisos del archivo
+    if req.peer_addr().is_some() {
+        let ClientIp(ip) = ClientIp::from_service_request(&req);
+        if !access.allows_ip(ip) {
+            warn!("Admin: acceso denegado a {} ({})", ip, req.path());
+            return Ok(req.into_response(AppError::Forbidden("Acceso denegado".to_string()).error_response()));
+        }
+    }
+
+    // Un certificado de cliente con alguno de los roles configurados basta
+    if ClientIdentity::from_http_request(req.request()).is_some_and(|identity| access.allows_certificate(&identity)) {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    }
+
+    if let Some(users) = access.users.clone() {
+        let valid = match basic_credentials(req.headers()) {
+            Some((username, password)) => web::block(move || users.verify(&username, &password))
+                .await
+                .unwrap_or(false),
+            None => false,
+        };
+        if !valid {
+            let mut res = AppError::Unauthorized("No autorizado".to_string()).error_response();
+            res.headers_mut().insert(
+                header::WWW_AUTHENTICATE,
+                HeaderValue::from_static("Basic realm=\"admin\", charset=\"UTF-8\""),
+            );
+            return Ok(req.into_response(res));
+        }
+    } else if !access.client_cert_roles.is_empty() {
+        let res = AppError::Unauthorized("Certificado de cliente requerido".to_string()).error_response();
+        return Ok(req.into_response(res));
+    }
+
+    Ok(next.call(req).await?.map_into_boxed_body())
+}
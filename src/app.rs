@@ -0,0 +1,238 @@
+// app.rs
+use crate::access::{self, AccessControl};
+use crate::client_ip::TrustedProxies;
+use crate::connections::{self, ConnectionTracker};
+use crate::config::{Config, LoginConfig};
+use crate::cors::{self, CorsPolicies};
+use crate::csrf::{self, Csrf};
+use crate::db::{Database, DatabaseCheck};
+use crate::dos::{self, DosGuard};
+use crate::error::{self, AppError};
+use crate::handlers;
+use crate::health::{self, DirectoryCheck, Health, Probe};
+use crate::items;
+use crate::logging::{self, RequestLogging};
+use crate::limits::{self, RequestLimits};
+use crate::login::{self, LoginGuard, UserStore};
+use crate::metrics::{self, Metrics};
+use crate::mtls::{self, MtlsAuthorizer};
+use crate::rate_limit::{self, RateLimiters};
+use crate::recovery;
+use crate::redirects::{self, Redirects};
+use crate::repository::{ItemRepository, SqlItemRepository};
+use crate::response_headers::{self, ResponseHeaders};
+use crate::security_headers::{self, SecurityHeaders};
+use actix_session::storage::CookieSessionStore;
+use actix_session::SessionMiddleware;
+use actix_web::body::MessageBody;
+use actix_web::cookie::Key;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::web::{self, Data};
+use actix_web::{middleware, App, Error, ResponseError};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::io;
+use std::sync::Arc;
+use tracing::warn;
+
+// Estado compartido por todos los workers de todos los listeners
+#[derive(Clone)]
+pub struct AppState {
+    pub trusted_proxies: Data<TrustedProxies>,
+    pub rate_limiters: Data<RateLimiters>,
+    pub login_guard: Data<LoginGuard>,
+    pub user_store: Data<UserStore>,
+    pub security_headers: Data<SecurityHeaders>,
+    pub csrf: Data<Csrf>,
+    pub cors_policies: Data<CorsPolicies>,
+    pub mtls_authorizer: Data<MtlsAuthorizer>,
+    pub metrics: Data<Metrics>,
+    pub connections: Data<ConnectionTracker>,
+    pub request_logging: Data<RequestLogging>,
+    pub health: Data<Health>,
+    pub request_limits: Data<RequestLimits>,
+    pub response_headers: Data<ResponseHeaders>,
+    pub redirects: Data<Redirects>,
+    pub dos_guard: Data<DosGuard>,
+    pub access: Data<AccessControl>,
+    pub database: Database,
+    pub items: Data<dyn ItemRepository>,
+    pub session_key: Key,
+}
+
+impl AppState {
+    pub fn new(config: &Config, metrics: Arc<Metrics>) -> io::Result<Self> {
+        let cors_policies = CorsPolicies::new(&config.cors).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("Patrón de origen CORS inválido: {}", e))
+        })?;
+
+        let connections = ConnectionTracker::new(&metrics.registry).map_err(|e| {
+            io::Error::other(format!("No se pudieron registrar las métricas de conexión: {}", e))
+        })?;
+
+        let dos_guard = DosGuard::new(&config.dos, &config.proxy.trusted_proxies, &metrics.registry).map_err(|e| {
+            io::Error::other(format!("No se pudieron registrar las métricas de protección DoS: {}", e))
+        })?;
+
+        let session_key = secret_key(&config.login)?;
+        let health = Health::default();
+        health.register(Probe::Readiness, Arc::new(DirectoryCheck::new("static_dir", "./static")));
+        // Las sesiones van en cookies cifradas: no hay almacén externo que comprobar
+
+        // El pool abre las conexiones al usarse; main aplica las migraciones antes de arrancar
+        let database = Database::connect(&config.database)?;
+        health.register(Probe::Readiness, Arc::new(DatabaseCheck::new(database.clone())));
+        let items: Arc<dyn ItemRepository> = Arc::new(SqlItemRepository::new(database.clone()));
+
+        Ok(AppState {
+            trusted_proxies: Data::new(TrustedProxies::new(config.proxy.trusted_proxies.clone())),
+            rate_limiters: Data::new(RateLimiters::new(&config.rate_limit)),
+            login_guard: Data::new(LoginGuard::new(config.login.clone())),
+            user_store: Data::new(UserStore::new(&config.login.users)),
+            security_headers: Data::new(SecurityHeaders::new(&config.security_headers)),
+            csrf: Data::new(Csrf::new(config.csrf.clone())),
+            cors_policies: Data::new(cors_policies),
+            mtls_authorizer: Data::new(MtlsAuthorizer::new(&config.mtls)),
+            metrics: Data::from(metrics),
+            connections: Data::new(connections),
+            request_logging: Data::new(RequestLogging::new(&config.logging)),
+            health: Data::new(health),
+            request_limits: Data::new(RequestLimits::new(&config.limits)),
+            response_headers: Data::new(ResponseHeaders::new(&config.response_headers)?),
+            redirects: Data::new(Redirects::new(&config.redirects)?),
+            dos_guard: Data::new(dos_guard),
+            access: Data::new(AccessControl::new(&config.access)?),
+            database,
+            items: Data::from(items),
+            session_key,
+        })
+    }
+}
+
+// Construye la aplicación completa; la usan todos los listeners
+pub fn build_app(
+    state: &AppState,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = Error,
+        InitError = (),
+    >,
+> {
+    App::new()
+        .app_data(state.trusted_proxies.clone())
+        .app_data(state.rate_limiters.clone())
+        .app_data(state.login_guard.clone())
+        .app_data(state.user_store.clone())
+        .app_data(state.security_headers.clone())
+        .app_data(state.csrf.clone())
+        .app_data(state.cors_policies.clone())
+        .app_data(state.mtls_authorizer.clone())
+        .app_data(state.metrics.clone())
+        .app_data(state.request_logging.clone())
+        .app_data(state.health.clone())
+        .app_data(state.request_limits.clone())
+        .app_data(state.response_headers.clone())
+        .app_data(state.redirects.clone())
+        .app_data(state.dos_guard.clone())
+        .app_data(state.access.clone())
+        .app_data(state.items.clone())
+        .app_data(
+            web::JsonConfig::default()
+                .limit(state.request_limits.json_limit_bytes)
+                .error_handler(|err, _req| payload_error(&err, "JSON inválido").into()),
+        )
+        .app_data(
+            web::FormConfig::default()
+                .limit(state.request_limits.form_limit_bytes)
+                .error_handler(|err, _req| payload_error(&err, "Formulario inválido").into()),
+        )
+        .app_data(web::QueryConfig::default().error_handler(|err, _req| AppError::BadRequest(format!("Parámetros inválidos: {}", err)).into()))
+        .app_data(web::PayloadConfig::default().limit(state.request_limits.largest_body_limit()))
+        .wrap(middleware::from_fn(csrf::csrf_middleware))
+        .wrap(
+            SessionMiddleware::builder(CookieSessionStore::default(), state.session_key.clone())
+                .cookie_same_site(actix_web::cookie::SameSite::Strict)
+                .build(),
+        )
+        .wrap(middleware::from_fn(mtls::mtls_middleware))
+        .wrap(middleware::from_fn(security_headers::security_headers_middleware))
+        .wrap(middleware::from_fn(cors::cors_middleware))
+        .wrap(middleware::from_fn(rate_limit::rate_limit_middleware))
+        // Los pánicos de los handlers y de los middlewares interiores se responden con un 500
+        .wrap(middleware::from_fn(recovery::panic_recovery_middleware))
+        // URL, cabeceras y cuerpo demasiado grandes (414, 431 y 413)
+        .wrap(middleware::from_fn(limits::limits_middleware))
+        // Clientes bloqueados y cuerpos demasiado lentos
+        .wrap(middleware::from_fn(dos::dos_middleware))
+        // Antes de los controles por ruta, para que vean la ruta reescrita
+        .wrap(middleware::from_fn(redirects::redirects_middleware))
+        // Redes denegadas o no permitidas, antes de cualquier otra respuesta
+        .wrap(middleware::from_fn(access::access_middleware))
+        // Dentro de Compress: los tamaños de respuesta son sin comprimir
+        .wrap(middleware::from_fn(metrics::metrics_middleware))
+        .wrap(middleware::from_fn(connections::connection_middleware))
+        // Formato común (problem details o página HTML) para todos los errores
+        .wrap(error::error_handlers())
+        // Cabeceras por ruta sobre la respuesta ya terminada, incluidas las de error.
+        // Dentro del log para disponer del ID de petición.
+        .wrap(middleware::from_fn(response_headers::response_headers_middleware))
+        // Dentro de Compress para poder añadir el ID de petición a los cuerpos de error
+        .wrap(middleware::from_fn(logging::request_logging_middleware))
+        .wrap(middleware::Compress::default())
+        .route("/", web::get().to(handlers::index_page))
+        .route("/index.js", web::get().to(handlers::index_script))
+        .route("/login", web::get().to(handlers::login_page))
+        .route("/login", web::post().to(login::login_handler))
+        .route("/login.js", web::get().to(handlers::login_script))
+        .route("/all.css", web::get().to(handlers::allcss_page))
+        .route("/csrf-token", web::get().to(csrf::csrf_token_handler))
+        .service(
+            web::scope("/api/items")
+                .route("", web::get().to(items::list_items))
+                .route("", web::post().to(items::create_item))
+                .route("/{id}", web::get().to(items::get_item))
+                .route("/{id}", web::put().to(items::replace_item))
+                .route("/{id}", web::patch().to(items::update_item))
+                .route("/{id}", web::delete().to(items::delete_item)),
+        )
+        .route("/healthz", web::get().to(health::public_healthz))
+        .route("/readyz", web::get().to(health::public_readyz))
+        .route("/livez", web::get().to(health::public_livez))
+        .route("/static/{filename:.*}", web::get().to(handlers::static_files))
+        .default_service(web::route().to(handlers::not_found))
+}
+
+// Los cuerpos que superan el límite son un 413; el resto de errores de lectura, un 400
+fn payload_error(err: &impl ResponseError, context: &str) -> AppError {
+    if err.status_code() == StatusCode::PAYLOAD_TOO_LARGE {
+        AppError::PayloadTooLarge(format!("{}: {}", context, err))
+    } else {
+        AppError::BadRequest(format!("{}: {}", context, err))
+    }
+}
+
+// Clave de las cookies de sesión: SESSION_KEY o login.session_key, en base64 y de al menos
+// 64 bytes. Sin clave se genera una aleatoria y las sesiones no sobreviven a un reinicio.
+fn secret_key(config: &LoginConfig) -> io::Result<Key> {
+    let encoded = match std::env::var("SESSION_KEY") {
+        Ok(value) => Some(value),
+        Err(_) => config.session_key.clone(),
+    };
+    let Some(encoded) = encoded else {
+        warn!("Sin clave de sesión configurada (SESSION_KEY o login.session_key): se genera una aleatoria y las sesiones se pierden al reiniciar");
+        return Ok(Key::generate());
+    };
+    let bytes = STANDARD.decode(encoded.trim()).map_err(|e| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("Clave de sesión inválida, se esperaba base64: {}", e))
+    })?;
+    Key::try_from(bytes.as_slice()).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Clave de sesión demasiado corta: {} bytes, hacen falta al menos 64", bytes.len()),
+        )
+    })
+}
@@ -0,0 +1,39 @@
+// audit.rs
+use std::net::IpAddr;
+use std::time::Duration;
+use tracing::info;
+
+// Eventos de seguridad que deben quedar registrados
+#[derive(Debug)]
+pub enum AuditEvent<'a> {
+    LoginSucceeded { username: &'a str, ip: IpAddr },
+    LoginFailed { username: &'a str, ip: IpAddr },
+    LoginRejectedLocked { username: &'a str, ip: IpAddr },
+    UserLocked { username: &'a str, ip: IpAddr, duration: Duration },
+    IpLocked { ip: IpAddr, duration: Duration },
+    IpBanned { ip: IpAddr, duration: Duration, reason: &'a str },
+}
+
+// Se emiten con el target "audit" para poder filtrarlos o enviarlos aparte
+pub fn record(event: &AuditEvent) {
+    match event {
+        AuditEvent::LoginSucceeded { username, ip } => {
+            info!(target: "audit", event = "login_succeeded", username, %ip)
+        }
+        AuditEvent::LoginFailed { username, ip } => {
+            info!(target: "audit", event = "login_failed", username, %ip)
+        }
+        AuditEvent::LoginRejectedLocked { username, ip } => {
+            info!(target: "audit", event = "login_rejected_locked", username, %ip)
+        }
+        AuditEvent::UserLocked { username, ip, duration } => {
+            info!(target: "audit", event = "user_locked", username, %ip, duration_secs = duration.as_secs())
+        }
+        AuditEvent::IpLocked { ip, duration } => {
+            info!(target: "audit", event = "ip_locked", %ip, duration_secs = duration.as_secs())
+        }
+        AuditEvent::IpBanned { ip, duration, reason } => {
+            info!(target: "audit", event = "ip_banned", %ip, duration_secs = duration.as_secs(), reason)
+        }
+    }
+}
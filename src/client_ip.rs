@@ -0,0 +1,180 @@
+// client_ip.rs
+use actix_web::dev::{Payload, ServiceRequest};
+use actix_web::http::header::{self, HeaderMap};
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest};
+use ipnet::IpNet;
+use std::fmt;
+use std::future::{ready, Ready};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+// Lista de proxies de confianza. Solo se leen las cabeceras de reenvío
+// cuando la conexión viene de una de estas redes.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    networks: Vec<IpNet>,
+}
+
+impl TrustedProxies {
+    pub fn new(networks: Vec<IpNet>) -> Self {
+        TrustedProxies { networks }
+    }
+
+    pub fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.networks.iter().any(|net| net.contains(&ip))
+    }
+
+    // Resuelve la IP real del cliente. Se recorre la cadena de saltos de derecha
+    // a izquierda y se devuelve el primero que no sea un proxy de confianza.
+    pub fn resolve(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !self.is_trusted(peer) {
+            return peer;
+        }
+
+        let mut client = peer;
+        for hop in forwarded_chain(headers).iter().rev() {
+            match hop {
+                Some(ip) => {
+                    client = *ip;
+                    if !self.is_trusted(*ip) {
+                        return client;
+                    }
+                }
+                // Un salto ilegible no es fiable: nos quedamos con el último conocido
+                None => return client,
+            }
+        }
+        client
+    }
+}
+
+// Cadena de saltos según Forwarded, X-Forwarded-For o X-Real-IP (en ese orden)
+fn forwarded_chain(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    let forwarded: Vec<Option<IpAddr>> = headers
+        .get_all("forwarded")
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|element| {
+            element.split(';').find_map(|pair| {
+                let (key, value) = pair.split_once('=')?;
+                key.trim().eq_ignore_ascii_case("for").then(|| parse_node(value))
+            })
+        })
+        .collect();
+    if !forwarded.is_empty() {
+        return forwarded;
+    }
+
+    let x_forwarded_for: Vec<Option<IpAddr>> = headers
+        .get_all("x-forwarded-for")
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(parse_node)
+        .collect();
+    if !x_forwarded_for.is_empty() {
+        return x_forwarded_for;
+    }
+
+    headers
+        .get("x-real-ip")
+        .and_then(|value| value.to_str().ok())
+        .map(|value| vec![parse_node(value)])
+        .unwrap_or_default()
+}
+
+// Acepta "1.2.3.4", "1.2.3.4:80", "[::1]", "[::1]:80" y "::1", con o sin comillas
+fn parse_node(value: &str) -> Option<IpAddr> {
+    let value = value.trim().trim_matches('"');
+
+    if let Some(rest) = value.strip_prefix('[') {
+        return rest.split(']').next()?.parse().ok();
+    }
+
+    value
+        .parse::<IpAddr>()
+        .ok()
+        .or_else(|| value.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+}
+
+// Extractor con la IP real del cliente
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ClientIp(pub IpAddr);
+
+impl ClientIp {
+    pub fn from_request_parts(req: &HttpRequest) -> ClientIp {
+        if let Some(ip) = req.extensions().get::<ClientIp>() {
+            return *ip;
+        }
+
+        // Sin dirección de par (p. ej. sockets Unix) se usa 0.0.0.0
+        let peer = req
+            .peer_addr()
+            .map(|addr| addr.ip())
+            .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+
+        let ip = match req.app_data::<web::Data<TrustedProxies>>() {
+            Some(proxies) => proxies.resolve(peer, req.headers()),
+            None => peer,
+        };
+
+        let client_ip = ClientIp(ip);
+        req.extensions_mut().insert(client_ip);
+        client_ip
+    }
+
+    pub fn from_service_request(req: &ServiceRequest) -> ClientIp {
+        ClientIp::from_request_parts(req.request())
+    }
+}
+
+// Esquema y host con los que el cliente hizo la petición. Forwarded y
+// X-Forwarded-Proto/Host solo cuentan si la conexión viene de un proxy de confianza.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestOrigin {
+    pub scheme: String,
+    pub host: String,
+}
+
+impl RequestOrigin {
+    pub fn from_request_parts(req: &HttpRequest) -> RequestOrigin {
+        let peer = req
+            .peer_addr()
+            .map(|addr| addr.ip())
+            .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        let behind_proxy = req
+            .app_data::<web::Data<TrustedProxies>>()
+            .is_some_and(|proxies| proxies.is_trusted(peer));
+        if behind_proxy {
+            let info = req.connection_info();
+            return RequestOrigin {
+                scheme: info.scheme().to_string(),
+                host: info.host().to_string(),
+            };
+        }
+
+        let host = req
+            .headers()
+            .get(header::HOST)
+            .and_then(|value| value.to_str().ok())
+            .or_else(|| req.uri().authority().map(|authority| authority.as_str()))
+            .unwrap_or_else(|| req.app_config().host());
+        RequestOrigin {
+            scheme: if req.app_config().secure() { "https" } else { "http" }.to_string(),
+            host: host.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ClientIp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromRequest for ClientIp {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(Ok(ClientIp::from_request_parts(req)))
+    }
+}
@@ -0,0 +1,803 @@
+// config.rs
+use ipnet::IpNet;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::{fs, io};
+
+// Ruta por defecto del archivo de configuración (se puede cambiar con SERVER_CONFIG)
+pub const DEFAULT_CONFIG_PATH: &str = "./config.toml";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub tls: TlsConfig,
+    pub proxy: ProxyConfig,
+    pub rate_limit: RateLimitConfig,
+    pub login: LoginConfig,
+    pub security_headers: SecurityHeadersConfig,
+    pub csrf: CsrfConfig,
+    pub cors: CorsConfig,
+    pub mtls: MtlsConfig,
+    pub metrics: MetricsConfig,
+    pub admin: AdminConfig,
+    pub logging: LoggingConfig,
+    pub tracing: TracingConfig,
+    pub limits: LimitsConfig,
+    pub response_headers: ResponseHeadersConfig,
+    pub redirects: RedirectsConfig,
+    pub dos: DosConfig,
+    pub access: AccessConfig,
+    pub database: DatabaseConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    // Todos los listeners comparten la misma aplicación y se arrancan y paran juntos
+    pub listeners: Vec<ListenerConfig>,
+    // Tiempo máximo para terminar las peticiones en curso al detener el servidor
+    pub shutdown_timeout_secs: u64,
+    // Tiempo que se sigue atendiendo con /readyz fallando antes de dejar de aceptar
+    // conexiones, para que el balanceador deje de enviar tráfico
+    pub shutdown_delay_secs: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            listeners: vec![ListenerConfig::default()],
+            shutdown_timeout_secs: 30,
+            shutdown_delay_secs: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListenerKind {
+    #[default]
+    Http,
+    Https,
+    Unix,
+}
+
+// Autenticación con certificado de cliente en un listener HTTPS
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClientAuth {
+    #[default]
+    None,
+    // Se pide certificado pero se aceptan conexiones sin él
+    Optional,
+    Required,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ListenerConfig {
+    pub name: String,
+    pub kind: ListenerKind,
+    // "ip:puerto", o la ruta del socket para los listeners Unix
+    pub bind: String,
+    // Solo HTTP: redirigir a HTTPS (y servir los retos ACME) en lugar de la aplicación
+    pub redirect_to_https: bool,
+    // Solo HTTPS: certificados de cliente validados contra mtls.ca_bundle
+    pub client_auth: ClientAuth,
+    // Solo TCP: SO_REUSEPORT, para que un binario nuevo escuche en el mismo puerto
+    // antes de detener el anterior
+    pub reuse_port: bool,
+    // Sirve la aplicación de administración (/metrics) en lugar de la principal
+    pub admin: bool,
+    pub workers: usize,
+    // Conexiones simultáneas del listener; por encima se cierran las nuevas. actix aplica
+    // además el mismo valor por worker (las de más esperan en la cola del kernel)
+    pub max_connections: usize,
+    pub max_connection_rate: usize,
+    pub keep_alive_secs: u64,
+    // Tiempo máximo para recibir la cabecera de la petición (protección slowloris);
+    // el del cuerpo está en [dos]
+    pub client_request_timeout_secs: u64,
+    pub client_disconnect_timeout_secs: u64,
+}
+
+impl Default for ListenerConfig {
+    fn default() -> Self {
+        ListenerConfig {
+            name: "http".to_string(),
+            kind: ListenerKind::Http,
+            bind: "127.0.0.1:80".to_string(),
+            redirect_to_https: false,
+            client_auth: ClientAuth::None,
+            reuse_port: false,
+            admin: false,
+            workers: 8,
+            max_connections: 50_000,
+            max_connection_rate: 1_000,
+            keep_alive_secs: 5,
+            client_request_timeout_secs: 30,
+            client_disconnect_timeout_secs: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TlsConfig {
+    // Certificados para los listeners HTTPS
+    pub certificates: Vec<CertificateConfig>,
+    pub acme_challenge_dir: String,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        TlsConfig {
+            certificates: Vec::new(),
+            acme_challenge_dir: "./acme/.well-known/acme-challenge".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CertificateConfig {
+    pub cert_path: String,
+    pub key_path: String,
+    // Nombres SNI servidos con este certificado ("*.example.com" admitido).
+    // Sin nombres, es el certificado por defecto.
+    #[serde(default)]
+    pub server_names: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ProxyConfig {
+    // Redes (CIDR) de los proxies en los que confiamos para leer
+    // Forwarded / X-Forwarded-For / X-Real-IP. Vacío = no se confía en nadie.
+    pub trusted_proxies: Vec<IpNet>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub global_per_minute: u32,
+    pub per_client_per_minute: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            global_per_minute: 6_000,
+            per_client_per_minute: 100,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LoginConfig {
+    // Fallos permitidos antes de bloquear un usuario o una IP
+    pub max_failures_per_user: u32,
+    pub max_failures_per_ip: u32,
+    // Duración del primer bloqueo; se duplica con cada fallo adicional hasta el máximo
+    pub base_lockout_secs: u64,
+    pub max_lockout_secs: u64,
+    // Los fallos más antiguos que esta ventana se olvidan
+    pub failure_window_secs: u64,
+    // Tiempo mínimo de respuesta para no revelar si el usuario existe
+    pub min_response_ms: u64,
+    pub users: Vec<UserCredentials>,
+    // Clave de las cookies de sesión en base64 (64 bytes o más); SESSION_KEY tiene prioridad
+    pub session_key: Option<String>,
+}
+
+impl Default for LoginConfig {
+    fn default() -> Self {
+        LoginConfig {
+            max_failures_per_user: 5,
+            max_failures_per_ip: 20,
+            base_lockout_secs: 30,
+            max_lockout_secs: 3_600,
+            failure_window_secs: 900,
+            min_response_ms: 500,
+            users: Vec::new(),
+            session_key: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct UserCredentials {
+    pub username: String,
+    // Hash Argon2 en formato PHC ($argon2id$v=19$...)
+    pub password_hash: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SecurityHeadersConfig {
+    pub enabled: bool,
+    pub default: SecurityPolicy,
+    // Ajustes por prefijo de ruta sobre la política por defecto
+    pub routes: Vec<RouteSecurityPolicy>,
+}
+
+impl Default for SecurityHeadersConfig {
+    fn default() -> Self {
+        SecurityHeadersConfig {
+            enabled: true,
+            default: SecurityPolicy::default(),
+            routes: Vec::new(),
+        }
+    }
+}
+
+// Valores de las cabeceras de seguridad. None o "" = no se envía.
+// En la CSP, "{nonce}" se sustituye por un nonce nuevo en cada respuesta.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SecurityPolicy {
+    pub strict_transport_security: Option<String>,
+    pub content_security_policy: Option<String>,
+    pub x_frame_options: Option<String>,
+    pub x_content_type_options: Option<String>,
+    pub referrer_policy: Option<String>,
+    pub permissions_policy: Option<String>,
+    pub cross_origin_opener_policy: Option<String>,
+    pub cross_origin_embedder_policy: Option<String>,
+}
+
+impl Default for SecurityPolicy {
+    fn default() -> Self {
+        SecurityPolicy {
+            strict_transport_security: Some("max-age=31536000; includeSubDomains".to_string()),
+            content_security_policy: Some(
+                "default-src 'self'; script-src 'self' 'nonce-{nonce}'; style-src 'self' 'nonce-{nonce}'; \
+                 img-src 'self' data:; object-src 'none'; base-uri 'self'; frame-ancestors 'none'"
+                    .to_string(),
+            ),
+            x_frame_options: Some("DENY".to_string()),
+            x_content_type_options: Some("nosniff".to_string()),
+            referrer_policy: Some("strict-origin-when-cross-origin".to_string()),
+            permissions_policy: Some("camera=(), microphone=(), geolocation=(), payment=()".to_string()),
+            cross_origin_opener_policy: Some("same-origin".to_string()),
+            cross_origin_embedder_policy: Some("require-corp".to_string()),
+        }
+    }
+}
+
+impl SecurityPolicy {
+    // Aplica los valores definidos en `other` sobre esta política
+    pub fn merge(&self, other: &SecurityPolicyOverride) -> SecurityPolicy {
+        let pick = |base: &Option<String>, over: &Option<String>| over.clone().or_else(|| base.clone());
+        SecurityPolicy {
+            strict_transport_security: pick(&self.strict_transport_security, &other.strict_transport_security),
+            content_security_policy: pick(&self.content_security_policy, &other.content_security_policy),
+            x_frame_options: pick(&self.x_frame_options, &other.x_frame_options),
+            x_content_type_options: pick(&self.x_content_type_options, &other.x_content_type_options),
+            referrer_policy: pick(&self.referrer_policy, &other.referrer_policy),
+            permissions_policy: pick(&self.permissions_policy, &other.permissions_policy),
+            cross_origin_opener_policy: pick(&self.cross_origin_opener_policy, &other.cross_origin_opener_policy),
+            cross_origin_embedder_policy: pick(&self.cross_origin_embedder_policy, &other.cross_origin_embedder_policy),
+        }
+    }
+}
+
+// Igual que SecurityPolicy pero sin valores por defecto: None = heredar, "" = quitar
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SecurityPolicyOverride {
+    pub strict_transport_security: Option<String>,
+    pub content_security_policy: Option<String>,
+    pub x_frame_options: Option<String>,
+    pub x_content_type_options: Option<String>,
+    pub referrer_policy: Option<String>,
+    pub permissions_policy: Option<String>,
+    pub cross_origin_opener_policy: Option<String>,
+    pub cross_origin_embedder_policy: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RouteSecurityPolicy {
+    pub path_prefix: String,
+    #[serde(flatten)]
+    pub policy: SecurityPolicyOverride,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ResponseHeadersConfig {
+    // Se aplican todas las reglas que coinciden, en el orden del archivo
+    pub rules: Vec<ResponseHeaderRule>,
+}
+
+// Cabeceras de respuesta por ruta y método. En los valores, "{request_id}",
+// "{method}" y "{path}" se sustituyen por los de la petición.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ResponseHeaderRule {
+    // Glob sobre la ruta: "*" no cruza "/", "**" sí (p. ej. "/static/**")
+    pub path: Option<String>,
+    // Alternativa al glob: expresión regular que debe cumplir la ruta completa
+    pub path_pattern: Option<String>,
+    // Vacío = cualquier método
+    pub methods: Vec<String>,
+    // Se añaden aunque ya exista la cabecera
+    pub add: BTreeMap<String, String>,
+    // Sustituyen el valor existente
+    pub set: BTreeMap<String, String>,
+    pub remove: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct RedirectsConfig {
+    // Archivo TOML con más reglas y mapas (mismo formato que esta sección).
+    // Se vigila junto con los CSV y se recarga al cambiar.
+    pub rules_file: Option<String>,
+    pub rules: Vec<RedirectRule>,
+    pub maps: Vec<RedirectMap>,
+}
+
+// Contenido del archivo `rules_file`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct RedirectRulesFile {
+    pub rules: Vec<RedirectRule>,
+    pub maps: Vec<RedirectMap>,
+}
+
+// Redirección o reescritura interna. `pattern` es una expresión regular que debe
+// cumplir la ruta completa; en `target`, "$1" o "${nombre}" toman sus capturas.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RedirectRule {
+    // Host exacto o comodín de subdominio ("*.example.com"). Sin valor = cualquiera
+    pub host: Option<String>,
+    pub pattern: String,
+    pub target: String,
+    // 301, 302, 307 o 308
+    #[serde(default = "default_redirect_status")]
+    pub status: u16,
+    // Servir `target` sin redirigir al cliente
+    #[serde(default)]
+    pub rewrite: bool,
+    // Añadir la query original al destino
+    #[serde(default = "default_true")]
+    pub preserve_query: bool,
+}
+
+// Redirecciones en bloque desde un CSV "origen,destino[,estado]" (rutas exactas)
+#[derive(Debug, Clone, Deserialize)]
+pub struct RedirectMap {
+    pub file: String,
+    // Estado de las líneas que no lo indican
+    #[serde(default = "default_redirect_status")]
+    pub status: u16,
+    #[serde(default = "default_true")]
+    pub preserve_query: bool,
+}
+
+fn default_redirect_status() -> u16 {
+    301
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CsrfConfig {
+    pub enabled: bool,
+    // Cabecera en la que el cliente devuelve el token
+    pub header_name: String,
+    // Orígenes externos aceptados además del propio sitio (p. ej. "https://app.example.com")
+    pub allowed_origins: Vec<String>,
+    // Prefijos de ruta sin comprobación CSRF
+    pub exempt_paths: Vec<String>,
+}
+
+impl Default for CsrfConfig {
+    fn default() -> Self {
+        CsrfConfig {
+            enabled: true,
+            header_name: "X-CSRF-Token".to_string(),
+            allowed_origins: Vec::new(),
+            exempt_paths: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CorsConfig {
+    pub default: CorsPolicyConfig,
+    // Políticas completas por prefijo de ruta (no heredan de la política por defecto)
+    pub routes: Vec<RouteCorsPolicy>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CorsPolicyConfig {
+    // Orígenes exactos, "*" o comodines de subdominio ("https://*.example.com")
+    pub allowed_origins: Vec<String>,
+    // Expresiones regulares que debe cumplir el origen completo
+    pub allowed_origin_patterns: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    // "*" acepta cualquier cabecera
+    pub allowed_headers: Vec<String>,
+    pub exposed_headers: Vec<String>,
+    pub supports_credentials: bool,
+    // Tiempo de caché de la respuesta preflight en el navegador
+    pub max_age_secs: u64,
+}
+
+impl Default for CorsPolicyConfig {
+    fn default() -> Self {
+        CorsPolicyConfig {
+            allowed_origins: Vec::new(),
+            allowed_origin_patterns: Vec::new(),
+            allowed_methods: ["GET", "POST", "PUT", "PATCH", "DELETE"].map(String::from).to_vec(),
+            allowed_headers: ["Content-Type", "Authorization", "X-CSRF-Token"].map(String::from).to_vec(),
+            exposed_headers: Vec::new(),
+            supports_credentials: false,
+            max_age_secs: 3_600,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RouteCorsPolicy {
+    pub path_prefix: String,
+    #[serde(flatten)]
+    pub policy: CorsPolicyConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MetricsConfig {
+    // Límites de los intervalos del histograma de duración (segundos)
+    pub duration_buckets: Vec<f64>,
+    // Límites de los histogramas de tamaño de petición y respuesta (bytes)
+    pub size_buckets: Vec<f64>,
+    // Máximo de patrones de ruta distintos como etiqueta; el resto se agrupa en "other"
+    pub max_routes: usize,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        MetricsConfig {
+            duration_buckets: vec![0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0],
+            size_buckets: vec![100.0, 1_000.0, 10_000.0, 100_000.0, 1_000_000.0, 10_000_000.0],
+            max_routes: 100,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessLogFormat {
+    // Common Log Format
+    Common,
+    // Combined Log Format (Common + Referer y User-Agent)
+    #[default]
+    Combined,
+    Json,
+    Off,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    // Nivel por defecto ("error", "warn", "info", "debug", "trace"); RUST_LOG tiene prioridad
+    pub level: String,
+    // Nivel por módulo, p. ej. "servidor::tls" = "debug", "actix_server" = "warn"
+    pub modules: BTreeMap<String, String>,
+    pub format: LogFormat,
+    pub access_log: AccessLogFormat,
+    pub request_id_header: String,
+    // Reutilizar el ID de petición recibido (p. ej. de un proxy) si es válido
+    pub trust_request_id: bool,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        LoggingConfig {
+            level: "info".to_string(),
+            modules: BTreeMap::new(),
+            format: LogFormat::Text,
+            access_log: AccessLogFormat::Combined,
+            request_id_header: "X-Request-Id".to_string(),
+            trust_request_id: true,
+        }
+    }
+}
+
+// Exportación de trazas OpenTelemetry (OTLP sobre HTTP/protobuf)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TracingConfig {
+    pub enabled: bool,
+    // URL completa del colector, p. ej. "http://127.0.0.1:4318/v1/traces"
+    pub endpoint: String,
+    pub service_name: String,
+    // Fracción de trazas nuevas que se muestrean (0.0 - 1.0)
+    pub sample_ratio: f64,
+    // Respetar la decisión de muestreo del traceparent recibido
+    pub parent_based: bool,
+    pub export_timeout_secs: u64,
+    // Cabeceras extra para el colector (p. ej. autenticación)
+    pub headers: BTreeMap<String, String>,
+}
+
+impl Default for TracingConfig {
+    fn default() -> Self {
+        TracingConfig {
+            enabled: false,
+            endpoint: "http://127.0.0.1:4318/v1/traces".to_string(),
+            service_name: "servidor".to_string(),
+            sample_ratio: 1.0,
+            parent_based: true,
+            export_timeout_secs: 10,
+            headers: BTreeMap::new(),
+        }
+    }
+}
+
+// Límites de tamaño de las peticiones. actix responde 431 por su cuenta con más de
+// 96 cabeceras o 128 KiB de cabecera, así que los límites útiles están por debajo.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LimitsConfig {
+    // Longitud máxima de ruta + query (414 URI Too Long)
+    pub max_uri_length: usize,
+    // Tamaño total y número de cabeceras (431 Request Header Fields Too Large)
+    pub max_header_bytes: usize,
+    pub max_header_count: usize,
+    // Tamaño máximo del cuerpo si ninguna regla de `routes` aplica (413 Payload Too Large)
+    pub max_body_bytes: usize,
+    // Límites de los extractores Json y Form
+    pub json_limit_bytes: usize,
+    pub form_limit_bytes: usize,
+    pub routes: Vec<RouteBodyLimit>,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        LimitsConfig {
+            max_uri_length: 8 * 1024,
+            max_header_bytes: 32 * 1024,
+            max_header_count: 64,
+            max_body_bytes: 1024 * 1024,
+            json_limit_bytes: 256 * 1024,
+            form_limit_bytes: 64 * 1024,
+            routes: Vec::new(),
+        }
+    }
+}
+
+// Tamaño máximo del cuerpo por prefijo de ruta y, opcionalmente, tipo de contenido
+#[derive(Debug, Clone, Deserialize)]
+pub struct RouteBodyLimit {
+    pub path_prefix: String,
+    // Tipo MIME sin parámetros, p. ej. "multipart/form-data". Sin valor = cualquiera
+    pub content_type: Option<String>,
+    pub max_body_bytes: usize,
+}
+
+// Protección contra abusos por cliente: conexiones simultáneas por IP, cuerpos
+// lentos y bloqueo temporal de las IP que acumulan infracciones
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DosConfig {
+    // Conexiones simultáneas por IP. 0 = sin límite
+    pub max_connections_per_ip: usize,
+    // Tiempo máximo para recibir el cuerpo completo. 0 = sin límite
+    pub body_timeout_secs: u64,
+    // Velocidad mínima del cuerpo pasado el margen inicial. 0 = sin mínimo
+    pub min_body_rate_bytes_per_sec: u64,
+    pub min_rate_grace_secs: u64,
+    // Infracciones (exceso de conexiones, cuerpo lento, límite de peticiones)
+    // dentro de la ventana que provocan un bloqueo. 0 = no se bloquea
+    pub ban_after_strikes: u32,
+    pub strike_window_secs: u64,
+    pub ban_secs: u64,
+    // Redes sin estos límites (además de los proxies de confianza)
+    pub exempt: Vec<IpNet>,
+}
+
+impl Default for DosConfig {
+    fn default() -> Self {
+        DosConfig {
+            max_connections_per_ip: 64,
+            body_timeout_secs: 60,
+            min_body_rate_bytes_per_sec: 512,
+            min_rate_grace_secs: 5,
+            ban_after_strikes: 10,
+            strike_window_secs: 60,
+            ban_secs: 600,
+            exempt: Vec::new(),
+        }
+    }
+}
+
+// Control de acceso por red (CIDR) con la IP del cliente tras los proxies de confianza
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AccessConfig {
+    // Se aplican todas las reglas cuyo prefijo coincide con la ruta
+    pub rules: Vec<AccessRule>,
+}
+
+// Redes denegadas y, si la lista no está vacía, las únicas permitidas. Los archivos
+// tienen una red (o IP) por línea, admiten comentarios con "#" y se recargan al cambiar.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AccessRule {
+    pub path_prefix: String,
+    pub allow: Vec<IpNet>,
+    pub allow_file: Option<String>,
+    pub deny: Vec<IpNet>,
+    pub deny_file: Option<String>,
+}
+
+impl Default for AccessRule {
+    fn default() -> Self {
+        AccessRule {
+            path_prefix: "/".to_string(),
+            allow: Vec::new(),
+            allow_file: None,
+            deny: Vec::new(),
+            deny_file: None,
+        }
+    }
+}
+
+// Base de datos. El motor se elige por el esquema de la URL: "sqlite:" (embebida)
+// o "postgres:". Con "sqlite::memory:" se usa una única conexión, ya que cada
+// conexión tendría su propia base de datos.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DatabaseConfig {
+    pub url: String,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    // Espera máxima por una conexión libre; pasado este tiempo se responde 503
+    pub acquire_timeout_secs: u64,
+    // 0 = sin límite
+    pub idle_timeout_secs: u64,
+    pub max_lifetime_secs: u64,
+    // Directorio con un subdirectorio por motor (sqlite/ y postgres/) que sustituye a
+    // las migraciones incluidas en el binario. También es donde `migrate new` las crea.
+    pub migrations_dir: Option<String>,
+    pub migrations_on_startup: StartupMigrations,
+}
+
+// Qué hacer al arrancar con las migraciones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StartupMigrations {
+    // Aplicar las pendientes
+    #[default]
+    Apply,
+    // No arrancar si hay migraciones pendientes o alguna aplicada ha cambiado
+    Verify,
+    // No comprobar nada
+    Skip,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            url: "sqlite://data/servidor.db".to_string(),
+            max_connections: 10,
+            min_connections: 0,
+            acquire_timeout_secs: 5,
+            idle_timeout_secs: 600,
+            max_lifetime_secs: 1800,
+            migrations_dir: None,
+            migrations_on_startup: StartupMigrations::Apply,
+        }
+    }
+}
+
+// Control de acceso de los listeners de administración
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AdminConfig {
+    // Redes (CIDR) con acceso. Vacío = cualquier IP
+    pub allowed_ips: Vec<IpNet>,
+    // Usuarios para autenticación básica (hash Argon2). Vacío = sin autenticación
+    pub users: Vec<UserCredentials>,
+    // Roles de certificado de cliente (ver [mtls]) que dan acceso sin autenticación básica
+    pub client_cert_roles: Vec<String>,
+    // Métricas del proceso (CPU, memoria, descriptores) y del runtime de tokio
+    pub process_metrics: bool,
+}
+
+impl Default for AdminConfig {
+    fn default() -> Self {
+        AdminConfig {
+            allowed_ips: Vec::new(),
+            users: Vec::new(),
+            client_cert_roles: Vec::new(),
+            process_metrics: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct MtlsConfig {
+    // Certificados (PEM) de las CA que emiten los certificados de cliente
+    pub ca_bundle: String,
+    pub identities: Vec<MtlsIdentityRule>,
+    // Prefijos de ruta que exigen un certificado de cliente con alguno de los roles
+    pub routes: Vec<MtlsRouteRule>,
+}
+
+// Roles asignados a un certificado según su CN y/o un nombre alternativo (DNS, URI o email).
+// Si se indican ambos, deben coincidir los dos.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MtlsIdentityRule {
+    pub common_name: Option<String>,
+    pub san: Option<String>,
+    pub roles: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MtlsRouteRule {
+    pub path_prefix: String,
+    pub roles: Vec<String>,
+}
+
+impl Config {
+    pub fn has_tls_listeners(&self) -> bool {
+        self.server.listeners.iter().any(|listener| listener.kind == ListenerKind::Https)
+    }
+
+    // Carga la configuración desde un archivo TOML. Si el archivo no existe
+    // se usan los valores por defecto; el aviso lo da quien llama (ver `Config::exists`).
+    pub fn load(path: &str) -> io::Result<Config> {
+        if !Config::exists(path) {
+            return Ok(Config::default());
+        }
+
+        let content = fs::read_to_string(path)?;
+        Config::from_toml(&content)
+    }
+
+    pub fn exists(path: &str) -> bool {
+        Path::new(path).is_file()
+    }
+
+    // Secciones del archivo tal como están escritas, para ver qué cambia al recargar
+    pub fn load_table(path: &str) -> io::Result<toml::Table> {
+        if !Config::exists(path) {
+            return Ok(toml::Table::new());
+        }
+
+        fs::read_to_string(path)?.parse().map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("Configuración inválida: {}", e))
+        })
+    }
+
+    pub fn from_toml(content: &str) -> io::Result<Config> {
+        toml::from_str(content).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("Configuración inválida: {}", e))
+        })
+    }
+
+    // Ruta del archivo de configuración según la variable de entorno SERVER_CONFIG
+    pub fn path_from_env() -> String {
+        std::env::var("SERVER_CONFIG").unwrap_or_else(|_| DEFAULT_CONFIG_PATH.to_string())
+    }
+}
@@ -0,0 +1,337 @@
+// connections.rs
+use crate::tls::CertStore;
+use actix_tls::accept::rustls_0_23::TlsStream;
+use actix_web::body::{BodySize, BoxBody, MessageBody};
+use actix_web::dev::{Extensions, ServiceRequest, ServiceResponse};
+use actix_web::http::header;
+use actix_web::middleware::Next;
+use actix_web::rt::net::TcpStream;
+use actix_web::web::Data;
+use actix_web::{Error, HttpResponse};
+use prometheus::core::{Collector, Desc};
+use prometheus::proto::MetricFamily;
+use prometheus::{IntCounterVec, IntGaugeVec, Opts, Registry};
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use serde::Serialize;
+use socket2::SockRef;
+use std::any::Any;
+use std::collections::HashMap;
+use std::net::{Shutdown, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+// Motivo de rechazo propio; los del control de DoS se registran con el suyo
+pub const MAX_CONNECTIONS: &str = "max_connections";
+
+// Datos de una conexión abierta
+#[derive(Debug)]
+pub struct ConnectionInfo {
+    pub id: u64,
+    pub listener: String,
+    pub peer: Option<SocketAddr>,
+    pub tls: bool,
+    pub opened_at: SystemTime,
+    started: Instant,
+    requests: AtomicU64,
+    // Bytes de los cuerpos HTTP (no incluye cabeceras ni TLS)
+    bytes_received: AtomicU64,
+    bytes_sent: AtomicU64,
+    current_request: Mutex<Option<String>>,
+}
+
+// Se guarda en los datos de la conexión; al cerrarse la conexión actix los
+// libera y la conexión deja de contarse como abierta
+pub struct ConnectionHandle {
+    tracker: Data<ConnectionTracker>,
+    info: Arc<ConnectionInfo>,
+}
+
+impl Drop for ConnectionHandle {
+    fn drop(&mut self) {
+        self.tracker.remove(&self.info);
+    }
+}
+
+// Métricas de conexión por listener y registro de las conexiones abiertas
+pub struct ConnectionTracker {
+    next_id: AtomicU64,
+    connections: Mutex<HashMap<u64, Arc<ConnectionInfo>>>,
+    open: IntGaugeVec,
+    capacity: IntGaugeVec,
+    accepted: IntCounterVec,
+    rejected: IntCounterVec,
+    requests: IntCounterVec,
+    handshakes_started: IntCounterVec,
+    handshakes_completed: IntCounterVec,
+}
+
+impl ConnectionTracker {
+    pub fn new(registry: &Registry) -> prometheus::Result<Self> {
+        let tracker = ConnectionTracker {
+            next_id: AtomicU64::new(1),
+            connections: Mutex::new(HashMap::new()),
+            open: IntGaugeVec::new(Opts::new("http_connections_open", "Conexiones abiertas"), &["listener"])?,
+            capacity: IntGaugeVec::new(
+                Opts::new("http_connections_max", "Máximo de conexiones simultáneas del listener"),
+                &["listener"],
+            )?,
+            accepted: IntCounterVec::new(
+                Opts::new("http_connections_accepted_total", "Conexiones aceptadas"),
+                &["listener"],
+            )?,
+            rejected: IntCounterVec::new(
+                Opts::new("http_connections_rejected_total", "Conexiones cerradas al aceptarlas, por motivo"),
+                &["listener", "reason"],
+            )?,
+            requests: IntCounterVec::new(
+                Opts::new("http_connection_requests_total", "Peticiones por conexión; reused=true si la conexión ya se había usado (keep-alive)"),
+                &["listener", "reused"],
+            )?,
+            handshakes_started: IntCounterVec::new(
+                Opts::new("tls_handshakes_started_total", "Handshakes TLS iniciados (ClientHello válido)"),
+                &["listener"],
+            )?,
+            handshakes_completed: IntCounterVec::new(
+                Opts::new("tls_handshakes_completed_total", "Handshakes TLS completados"),
+                &["listener"],
+            )?,
+        };
+
+        registry.register(Box::new(tracker.open.clone()))?;
+        registry.register(Box::new(tracker.capacity.clone()))?;
+        registry.register(Box::new(tracker.accepted.clone()))?;
+        registry.register(Box::new(tracker.rejected.clone()))?;
+        registry.register(Box::new(tracker.requests.clone()))?;
+        registry.register(Box::new(tracker.handshakes_started.clone()))?;
+        registry.register(Box::new(tracker.handshakes_completed.clone()))?;
+        registry.register(Box::new(HandshakeFailures::new(
+            tracker.handshakes_started.clone(),
+            tracker.handshakes_completed.clone(),
+        )?))?;
+        Ok(tracker)
+    }
+
+    pub fn set_capacity(&self, listener: &str, capacity: usize) {
+        self.capacity.with_label_values(&[listener]).set(capacity as i64);
+    }
+
+    // Conexión cerrada nada más aceptarla (límite del listener o control de DoS)
+    pub fn reject(&self, listener: &str, reason: &str) {
+        self.rejected.with_label_values(&[listener, reason]).inc();
+    }
+
+    // Callback on_connect: registra la conexión en cuanto se acepta (tras el handshake TLS).
+    // Si el listener ya tiene max_connections abiertas, la cierra.
+    pub fn on_connect(tracker: &Data<ConnectionTracker>, listener: &str, connection: &dyn Any, data: &mut Extensions) {
+        let tls = connection.is::<TlsStream<TcpStream>>();
+        let tcp = tcp_stream(connection);
+        let peer = tcp.and_then(|tcp| tcp.peer_addr().ok());
+
+        let capacity = tracker.capacity.with_label_values(&[listener]).get();
+        if let Some(tcp) = tcp.filter(|_| capacity > 0 && tracker.open.with_label_values(&[listener]).get() >= capacity) {
+            warn!("Listener '{}' con el máximo de conexiones abiertas; se cierra la de {:?}", listener, peer);
+            tracker.reject(listener, MAX_CONNECTIONS);
+            if let Err(e) = SockRef::from(tcp).shutdown(Shutdown::Both) {
+                warn!("No se pudo cerrar la conexión: {}", e);
+            }
+            return;
+        }
+
+        let info = Arc::new(ConnectionInfo {
+            id: tracker.next_id.fetch_add(1, Ordering::Relaxed),
+            listener: listener.to_string(),
+            peer,
+            tls,
+            opened_at: SystemTime::now(),
+            started: Instant::now(),
+            requests: AtomicU64::new(0),
+            bytes_received: AtomicU64::new(0),
+            bytes_sent: AtomicU64::new(0),
+            current_request: Mutex::new(None),
+        });
+
+        tracker.accepted.with_label_values(&[listener]).inc();
+        tracker.open.with_label_values(&[listener]).inc();
+        if tls {
+            tracker.handshakes_completed.with_label_values(&[listener]).inc();
+        }
+        tracker.lock().insert(info.id, info.clone());
+
+        data.insert(ConnectionHandle {
+            tracker: tracker.clone(),
+            info,
+        });
+    }
+
+    fn remove(&self, info: &ConnectionInfo) {
+        self.lock().remove(&info.id);
+        self.open.with_label_values(&[&info.listener]).dec();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u64, Arc<ConnectionInfo>>> {
+        self.connections.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // Copia de las conexiones abiertas, de la más antigua a la más reciente
+    pub fn snapshot(&self) -> Vec<ConnectionSnapshot> {
+        let mut connections: Vec<ConnectionSnapshot> = self
+            .lock()
+            .values()
+            .map(|info| ConnectionSnapshot {
+                id: info.id,
+                listener: info.listener.clone(),
+                peer: info.peer.map(|peer| peer.to_string()),
+                tls: info.tls,
+                opened_at: info.opened_at.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default(),
+                age_secs: info.started.elapsed().as_secs_f64(),
+                requests: info.requests.load(Ordering::Relaxed),
+                bytes_received: info.bytes_received.load(Ordering::Relaxed),
+                bytes_sent: info.bytes_sent.load(Ordering::Relaxed),
+                current_request: info.current_request.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone(),
+            })
+            .collect();
+        connections.sort_by_key(|connection| connection.id);
+        connections
+    }
+
+    // Envuelve el almacén de certificados para contar los handshakes iniciados en el listener
+    pub fn counting_resolver(&self, listener: &str, store: Arc<CertStore>) -> Arc<dyn ResolvesServerCert> {
+        Arc::new(CountingResolver {
+            store,
+            started: self.handshakes_started.with_label_values(&[listener]),
+        })
+    }
+}
+
+// Socket TCP de la conexión (directo o bajo TLS); None en los sockets Unix
+pub fn tcp_stream(connection: &dyn Any) -> Option<&TcpStream> {
+    if let Some(tcp) = connection.downcast_ref::<TcpStream>() {
+        Some(tcp)
+    } else {
+        connection.downcast_ref::<TlsStream<TcpStream>>().map(|tls| tls.get_ref().0)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConnectionSnapshot {
+    pub id: u64,
+    pub listener: String,
+    pub peer: Option<String>,
+    pub tls: bool,
+    // Segundos desde la época Unix
+    pub opened_at: u64,
+    pub age_secs: f64,
+    pub requests: u64,
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+    pub current_request: Option<String>,
+}
+
+#[derive(Debug)]
+struct CountingResolver {
+    store: Arc<CertStore>,
+    started: prometheus::IntCounter,
+}
+
+impl ResolvesServerCert for CountingResolver {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        self.started.inc();
+        self.store.resolve(client_hello)
+    }
+}
+
+// rustls no avisa de los handshakes fallidos: se estiman como iniciados - completados.
+// Incluye los que están en curso en el momento de la lectura; las conexiones que ni
+// siquiera envían un ClientHello válido no se cuentan.
+struct HandshakeFailures {
+    started: IntCounterVec,
+    completed: IntCounterVec,
+    failures: IntGaugeVec,
+}
+
+impl HandshakeFailures {
+    fn new(started: IntCounterVec, completed: IntCounterVec) -> prometheus::Result<Self> {
+        Ok(HandshakeFailures {
+            started,
+            completed,
+            failures: IntGaugeVec::new(
+                Opts::new("tls_handshake_failures", "Handshakes TLS fallidos (estimado: iniciados - completados)"),
+                &["listener"],
+            )?,
+        })
+    }
+}
+
+impl Collector for HandshakeFailures {
+    fn desc(&self) -> Vec<&Desc> {
+        self.failures.desc()
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        for family in self.started.collect() {
+            for metric in family.get_metric() {
+                let Some(listener) = metric.get_label().iter().find(|label| label.get_name() == "listener") else {
+                    continue;
+                };
+                let listener = listener.get_value();
+                let started = metric.get_counter().get_value() as i64;
+                let completed = self.completed.with_label_values(&[listener]).get() as i64;
+                self.failures.with_label_values(&[listener]).set((started - completed).max(0));
+            }
+        }
+        self.failures.collect()
+    }
+}
+
+// Anota en la conexión la petición en curso, el número de peticiones y los bytes
+pub async fn connection_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let handle = req.conn_data::<ConnectionHandle>().map(|handle| (handle.tracker.clone(), handle.info.clone()));
+    let Some((tracker, info)) = handle else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+
+    let reused = info.requests.fetch_add(1, Ordering::Relaxed) > 0;
+    tracker
+        .requests
+        .with_label_values(&[&info.listener, if reused { "true" } else { "false" }])
+        .inc();
+
+    if let Some(size) = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok())
+    {
+        info.bytes_received.fetch_add(size, Ordering::Relaxed);
+    }
+
+    let request_line = format!("{} {}", req.method(), req.path());
+    *info.current_request.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(request_line.clone());
+
+    let result = next.call(req).await;
+
+    {
+        // Con HTTP/2 puede haber otra petición en curso en la misma conexión
+        let mut current = info.current_request.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if current.as_deref() == Some(request_line.as_str()) {
+            *current = None;
+        }
+    }
+
+    let res = result?.map_into_boxed_body();
+    if let BodySize::Sized(size) = res.response().body().size() {
+        info.bytes_sent.fetch_add(size, Ordering::Relaxed);
+    }
+    Ok(res)
+}
+
+// GET /connections (listener de administración): conexiones abiertas en JSON
+pub async fn connections_handler(tracker: Data<ConnectionTracker>) -> HttpResponse {
+    HttpResponse::Ok().json(tracker.snapshot())
+}
@@ -0,0 +1,232 @@
+// cors.rs
+use crate::client_ip::RequestOrigin;
+use crate::config::{CorsConfig, CorsPolicyConfig};
+use crate::error::AppError;
+use crate::paths;
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderMap, HeaderValue};
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use actix_web::web::Data;
+use actix_web::{Error, HttpRequest, HttpResponse, ResponseError};
+use regex::Regex;
+use tracing::warn;
+
+// Política CORS ya compilada
+pub struct CorsPolicy {
+    any_origin: bool,
+    exact_origins: Vec<String>,
+    // "https://*.example.com" -> ("https://", ".example.com")
+    wildcard_origins: Vec<(String, String)>,
+    origin_patterns: Vec<Regex>,
+    methods: Vec<Method>,
+    // None = se acepta cualquier cabecera
+    headers: Option<Vec<String>>,
+    exposed_headers: String,
+    supports_credentials: bool,
+    max_age_secs: u64,
+}
+
+impl CorsPolicy {
+    pub fn new(config: &CorsPolicyConfig) -> Result<Self, regex::Error> {
+        let mut policy = CorsPolicy {
+            any_origin: false,
+            exact_origins: Vec::new(),
+            wildcard_origins: Vec::new(),
+            origin_patterns: Vec::new(),
+            methods: config
+                .allowed_methods
+                .iter()
+                .filter_map(|method| Method::from_bytes(method.to_ascii_uppercase().as_bytes()).ok())
+                .collect(),
+            headers: (!config.allowed_headers.iter().any(|h| h == "*"))
+                .then(|| config.allowed_headers.iter().map(|h| h.to_ascii_lowercase()).collect()),
+            exposed_headers: config.exposed_headers.join(", "),
+            supports_credentials: config.supports_credentials,
+            max_age_secs: config.max_age_secs,
+        };
+
+        for origin in &config.allowed_origins {
+            let origin = origin.to_ascii_lowercase();
+            if origin == "*" {
+                policy.any_origin = true;
+            } else if let Some((prefix, suffix)) = origin.split_once("*.") {
+                policy.wildcard_origins.push((prefix.to_string(), format!(".{}", suffix)));
+            } else {
+                policy.exact_origins.push(origin);
+            }
+        }
+
+        for pattern in &config.allowed_origin_patterns {
+            policy.origin_patterns.push(Regex::new(&format!("^(?:{})$", pattern))?);
+        }
+
+        if policy.any_origin && policy.supports_credentials {
+            warn!("Advertencia: CORS con credenciales para cualquier origen; se reflejará el origen de cada petición");
+        }
+        Ok(policy)
+    }
+
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        if self.any_origin {
+            return true;
+        }
+
+        let origin = origin.to_ascii_lowercase();
+        self.exact_origins.contains(&origin)
+            || self.wildcard_origins.iter().any(|(prefix, suffix)| {
+                origin
+                    .strip_prefix(prefix.as_str())
+                    .and_then(|rest| rest.strip_suffix(suffix.as_str()))
+                    .is_some_and(|subdomain| {
+                        !subdomain.is_empty() && !subdomain.contains(['/', ':', '@'])
+                    })
+            })
+            || self.origin_patterns.iter().any(|pattern| pattern.is_match(&origin))
+    }
+
+    fn allows_method(&self, method: &str) -> bool {
+        self.methods.iter().any(|allowed| allowed.as_str().eq_ignore_ascii_case(method))
+    }
+
+    fn allows_headers(&self, requested: &str) -> bool {
+        let Some(allowed) = &self.headers else {
+            return true;
+        };
+        requested
+            .split(',')
+            .map(|h| h.trim().to_ascii_lowercase())
+            .filter(|h| !h.is_empty())
+            .all(|h| allowed.contains(&h))
+    }
+
+    // Access-Control-Allow-Origin: "*" solo si no hay credenciales
+    fn allow_origin_value(&self, origin: &HeaderValue) -> HeaderValue {
+        if self.any_origin && !self.supports_credentials {
+            HeaderValue::from_static("*")
+        } else {
+            origin.clone()
+        }
+    }
+
+    fn add_common_headers(&self, headers: &mut HeaderMap, origin: &HeaderValue) {
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, self.allow_origin_value(origin));
+        if self.supports_credentials {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_CREDENTIALS, HeaderValue::from_static("true"));
+        }
+        headers.append(header::VARY, HeaderValue::from_static("Origin"));
+    }
+
+    fn preflight_response(&self, req: &HttpRequest, origin: &HeaderValue) -> HttpResponse {
+        let requested_method = req
+            .headers()
+            .get(header::ACCESS_CONTROL_REQUEST_METHOD)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        let requested_headers = req
+            .headers()
+            .get(header::ACCESS_CONTROL_REQUEST_HEADERS)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+
+        if !self.allows_method(requested_method) {
+            return AppError::Forbidden("Método CORS no permitido".to_string()).error_response();
+        }
+        if !self.allows_headers(requested_headers) {
+            return AppError::Forbidden("Cabeceras CORS no permitidas".to_string()).error_response();
+        }
+
+        let methods = self.methods.iter().map(Method::as_str).collect::<Vec<_>>().join(", ");
+        let mut res = HttpResponse::NoContent();
+        res.insert_header((header::ACCESS_CONTROL_ALLOW_METHODS, methods))
+            .insert_header((header::ACCESS_CONTROL_MAX_AGE, self.max_age_secs.to_string()));
+        if !requested_headers.is_empty() {
+            res.insert_header((header::ACCESS_CONTROL_ALLOW_HEADERS, requested_headers));
+        }
+
+        let mut res = res.finish();
+        self.add_common_headers(res.headers_mut(), origin);
+        res.headers_mut().append(header::VARY, HeaderValue::from_static("Access-Control-Request-Method"));
+        res.headers_mut().append(header::VARY, HeaderValue::from_static("Access-Control-Request-Headers"));
+        res
+    }
+}
+
+// Política por defecto y políticas por prefijo de ruta
+pub struct CorsPolicies {
+    default: CorsPolicy,
+    routes: Vec<(String, CorsPolicy)>,
+}
+
+impl CorsPolicies {
+    pub fn new(config: &CorsConfig) -> Result<Self, regex::Error> {
+        let mut routes = config
+            .routes
+            .iter()
+            .map(|route| Ok((route.path_prefix.clone(), CorsPolicy::new(&route.policy)?)))
+            .collect::<Result<Vec<_>, regex::Error>>()?;
+        // El prefijo más largo tiene prioridad
+        routes.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+
+        Ok(CorsPolicies {
+            default: CorsPolicy::new(&config.default)?,
+            routes,
+        })
+    }
+
+    pub fn policy_for(&self, path: &str) -> &CorsPolicy {
+        self.routes
+            .iter()
+            .find(|(prefix, _)| paths::matches_prefix(path, prefix))
+            .map(|(_, policy)| policy)
+            .unwrap_or(&self.default)
+    }
+}
+
+// Origen del propio sitio según la petición ("https://host:puerto")
+pub fn own_origin(req: &HttpRequest) -> String {
+    let origin = RequestOrigin::from_request_parts(req);
+    format!("{}://{}", origin.scheme, origin.host)
+}
+
+pub async fn cors_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let (Some(policies), Some(origin)) = (
+        req.app_data::<Data<CorsPolicies>>().cloned(),
+        req.headers().get(header::ORIGIN).cloned(),
+    ) else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+
+    // Las peticiones del propio sitio no son CORS
+    let origin_str = origin.to_str().unwrap_or_default();
+    if origin_str.eq_ignore_ascii_case(&own_origin(req.request())) {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    }
+
+    let policy = policies.policy_for(paths::route_path(&req));
+    if !policy.allows_origin(origin_str) {
+        warn!("CORS: origen {:?} no permitido para {}", origin_str, req.path());
+        return Ok(req.into_response(AppError::Forbidden("Origen CORS no permitido".to_string()).error_response()));
+    }
+
+    let is_preflight = req.method() == Method::OPTIONS
+        && req.headers().contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+    if is_preflight {
+        let res = policy.preflight_response(req.request(), &origin);
+        return Ok(req.into_response(res));
+    }
+
+    let mut res = next.call(req).await?.map_into_boxed_body();
+    let headers = res.headers_mut();
+    policy.add_common_headers(headers, &origin);
+    if !policy.exposed_headers.is_empty() {
+        if let Ok(exposed) = HeaderValue::from_str(&policy.exposed_headers) {
+            headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, exposed);
+        }
+    }
+    Ok(res)
+}
@@ -0,0 +1,148 @@
+// csrf.rs
+use crate::config::CsrfConfig;
+use crate::cors;
+use crate::error::AppError;
+use crate::html_template;
+use crate::login::{self, BearerAuth};
+use crate::paths;
+use actix_session::{Session, SessionExt};
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::{header, Method};
+use actix_web::middleware::Next;
+use actix_web::web::Data;
+use actix_web::{Error, HttpResponse, ResponseError};
+use rand::distributions::Alphanumeric;
+use rand::rngs::OsRng;
+use rand::Rng;
+use subtle::ConstantTimeEq;
+use tracing::{error, info_span, warn};
+
+const SESSION_KEY: &str = "csrf_token";
+const HTML_TOKEN_PLACEHOLDER: &str = "{{csrf_token}}";
+
+pub struct Csrf {
+    config: CsrfConfig,
+}
+
+impl Csrf {
+    pub fn new(config: CsrfConfig) -> Self {
+        Csrf { config }
+    }
+
+    fn is_exempt(&self, req: &ServiceRequest) -> bool {
+        if self.config.exempt_paths.iter().any(|prefix| paths::matches_prefix(paths::route_path(req), prefix)) {
+            return true;
+        }
+
+        // Un sitio ajeno no puede conocer el token de la sesión: las llamadas de API con
+        // un token Bearer válido no necesitan el token CSRF
+        login::bearer_auth(req.request()) == BearerAuth::Valid
+    }
+
+    // El Origin (o en su defecto el Referer) debe ser el propio sitio o uno permitido
+    fn origin_allowed(&self, req: &ServiceRequest) -> bool {
+        let origin = match req.headers().get(header::ORIGIN) {
+            Some(origin) => origin.to_str().ok().map(str::to_string),
+            None => req
+                .headers()
+                .get(header::REFERER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(origin_of),
+        };
+        let Some(origin) = origin else {
+            return false;
+        };
+
+        origin.eq_ignore_ascii_case(&cors::own_origin(req.request()))
+            || self.config.allowed_origins.iter().any(|allowed| origin.eq_ignore_ascii_case(allowed))
+    }
+
+    // Motivo del rechazo de una petición que modifica estado, si lo hay
+    fn rejection(&self, req: &ServiceRequest) -> Option<&'static str> {
+        if is_safe_method(req.method()) || self.is_exempt(req) {
+            return None;
+        }
+
+        if !self.origin_allowed(req) {
+            warn!("CSRF: origen no permitido para {} {}", req.method(), req.path());
+            return Some("Origen no permitido");
+        }
+
+        let expected = req.get_session().get::<String>(SESSION_KEY).ok().flatten();
+        let provided = req
+            .headers()
+            .get(self.config.header_name.as_str())
+            .and_then(|value| value.to_str().ok());
+
+        let valid = match (expected, provided) {
+            (Some(expected), Some(provided)) => bool::from(expected.as_bytes().ct_eq(provided.as_bytes())),
+            _ => false,
+        };
+        if !valid {
+            warn!("CSRF: token ausente o inválido para {} {}", req.method(), req.path());
+            return Some("Token CSRF inválido");
+        }
+        None
+    }
+}
+
+// "https://host:port/ruta?x" -> "https://host:port"
+fn origin_of(url: &str) -> Option<String> {
+    let (scheme, rest) = url.split_once("://")?;
+    let host = rest.split(['/', '?', '#']).next()?;
+    (!host.is_empty()).then(|| format!("{}://{}", scheme, host))
+}
+
+fn is_safe_method(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE)
+}
+
+// Devuelve el token de la sesión, creándolo si todavía no existe
+pub fn session_token(session: &Session) -> Option<String> {
+    if let Ok(Some(token)) = session.get::<String>(SESSION_KEY) {
+        return Some(token);
+    }
+
+    let token: String = OsRng.sample_iter(&Alphanumeric).take(43).map(char::from).collect();
+    match session.insert(SESSION_KEY, &token) {
+        Ok(()) => Some(token),
+        Err(e) => {
+            error!("Error al guardar el token CSRF en la sesión: {}", e);
+            None
+        }
+    }
+}
+
+pub async fn csrf_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let csrf = match req.app_data::<Data<Csrf>>() {
+        Some(csrf) if csrf.config.enabled => csrf.clone(),
+        _ => return Ok(next.call(req).await?.map_into_boxed_body()),
+    };
+
+    if let Some(reason) = info_span!("csrf").in_scope(|| csrf.rejection(&req)) {
+        return Ok(req.into_response(AppError::Forbidden(reason.to_string()).error_response()));
+    }
+
+    let session = req.get_session();
+    let res = next.call(req).await?.map_into_boxed_body();
+
+    // Las páginas HTML reciben el token en el marcador {{csrf_token}}
+    if html_template::is_html(&res) {
+        return html_template::replace_placeholder(res, HTML_TOKEN_PLACEHOLDER, || session_token(&session)).await;
+    }
+    Ok(res)
+}
+
+// GET /csrf-token: token para clientes que no cargan nuestras páginas HTML
+pub async fn csrf_token_handler(session: Session) -> HttpResponse {
+    match session_token(&session) {
+        Some(token) => HttpResponse::Ok()
+            .append_header((header::CACHE_CONTROL, "no-store"))
+            .json(serde_json::json!({ "token": token })),
+        None => AppError::Internal("No se pudo generar el token CSRF".to_string()).error_response(),
+    }
+}
@@ -3,9 +3,10 @@ use std::path::Path;
 use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use tokio::io::AsyncWriteExt;
 use tokio::sync::mpsc;
+use tracing::{debug, error, info};
 
 // Combinar todos los archivos CSS en uno
-pub(crate) async fn combine_css(css_dir: &str, output_file: &str) -> io::Result<()> {
+pub async fn combine_css(css_dir: &str, output_file: &str) -> io::Result<()> {
     let mut output = tokio::fs::File::create(output_file).await?;
     let mut stack = vec![Path::new(css_dir).to_path_buf()];
 
@@ -21,18 +22,18 @@ pub(crate) async fn combine_css(css_dir: &str, output_file: &str) -> io::Result<
                     .write_all(format!("/* {} */\n", path.display()).as_bytes())
                     .await?;
                 output.write_all(content.as_bytes()).await?;
-                println!("CSS añadido: {}", path.display());
+                debug!("CSS añadido: {}", path.display());
             }
         }
     }
 
-    println!("CSS combinado en '{}'", output_file);
+    info!("CSS combinado en '{}'", output_file);
     Ok(())
 }
 
 // Monitorear cambios en CSS
-pub(crate) async fn monitor_changes(css_dir: &str, output_file: &str) -> io::Result<()> {
-    println!("Monitoreando cambios en '{}'", css_dir);
+pub async fn monitor_changes(css_dir: &str, output_file: &str) -> io::Result<()> {
+    info!("Monitoreando cambios en '{}'", css_dir);
 
     let (tx, mut rx) = mpsc::channel(1);
 
@@ -53,13 +54,13 @@ pub(crate) async fn monitor_changes(css_dir: &str, output_file: &str) -> io::Res
     while let Some(event) = rx.recv().await {
         match event.kind {
             EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) => {
-                println!("Detectado cambio: {:?}", event);
+                debug!("Detectado cambio: {:?}", event);
                 if let Err(e) = combine_css(css_dir, output_file).await {
-                    eprintln!("Error al combinar CSS: {}", e);
+                    error!("Error al combinar CSS: {}", e);
                 }
             }
             _ => {
-                println!("Evento ignorado: {:?}", event.kind);
+                debug!("Evento ignorado: {:?}", event.kind);
             }
         }
     }
@@ -0,0 +1,162 @@
+// db.rs
+use crate::config::DatabaseConfig;
+use crate::error::AppError;
+use crate::health::HealthCheck;
+use futures_util::future::BoxFuture;
+use serde_json::{json, Value};
+use sqlx::pool::PoolOptions;
+use sqlx::postgres::{PgConnectOptions, PgPool};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+// Ejecuta la misma expresión con el pool del motor que corresponda. Las consultas
+// usan parámetros "$1", "$2"… que entienden los dos motores.
+macro_rules! with_pool {
+    ($pool:expr, |$conn:ident| $body:expr) => {
+        match $pool {
+            $crate::db::DbPool::Sqlite($conn) => $body,
+            $crate::db::DbPool::Postgres($conn) => $body,
+        }
+    };
+}
+pub(crate) use with_pool;
+
+#[derive(Debug, Clone)]
+pub enum DbPool {
+    Sqlite(SqlitePool),
+    Postgres(PgPool),
+}
+
+// Pool de conexiones y migraciones del motor configurado
+#[derive(Debug, Clone)]
+pub struct Database {
+    pool: DbPool,
+    migrations_dir: Option<PathBuf>,
+}
+
+impl Database {
+    // Crea el pool sin abrir conexiones: se abren al usarlo por primera vez
+    pub fn connect(config: &DatabaseConfig) -> io::Result<Self> {
+        let url = config.url.as_str();
+        let pool = if url.starts_with("sqlite:") {
+            let options = SqliteConnectOptions::from_str(url).map_err(invalid_url)?.create_if_missing(true);
+            let in_memory = url.contains(":memory:") || url.contains("mode=memory");
+            if !in_memory {
+                if let Some(parent) = options.get_filename().parent().filter(|parent| !parent.as_os_str().is_empty()) {
+                    fs::create_dir_all(parent)?;
+                }
+            }
+            let pool_options = if in_memory {
+                // La base de datos en memoria desaparece al cerrarse su última conexión
+                SqlitePoolOptions::new().max_connections(1).idle_timeout(None).max_lifetime(None)
+            } else {
+                pool_options(config)
+            };
+            DbPool::Sqlite(pool_options.acquire_timeout(acquire_timeout(config)).connect_lazy_with(options))
+        } else if url.starts_with("postgres:") || url.starts_with("postgresql:") {
+            let options = PgConnectOptions::from_str(url).map_err(invalid_url)?;
+            DbPool::Postgres(pool_options::<sqlx::Postgres>(config).acquire_timeout(acquire_timeout(config)).connect_lazy_with(options))
+        } else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "URL de base de datos no admitida (se esperaba sqlite: o postgres:)",
+            ));
+        };
+
+        Ok(Database {
+            migrations_dir: config.migrations_dir.as_ref().map(|dir| Path::new(dir).join(backend_name(&pool))),
+            pool,
+        })
+    }
+
+    pub fn pool(&self) -> &DbPool {
+        &self.pool
+    }
+
+    pub fn backend(&self) -> &'static str {
+        backend_name(&self.pool)
+    }
+
+    // Directorio de migraciones del motor; None = las incluidas en el binario
+    pub fn migrations_dir(&self) -> Option<&Path> {
+        self.migrations_dir.as_deref()
+    }
+
+    pub async fn ping(&self) -> Result<(), sqlx::Error> {
+        with_pool!(&self.pool, |pool| sqlx::query("SELECT 1").execute(pool).await.map(|_| ()))
+    }
+
+    // Conexiones abiertas, libres y máximo del pool
+    pub fn pool_status(&self) -> Value {
+        let (size, idle, max) = with_pool!(&self.pool, |pool| (pool.size(), pool.num_idle(), pool.options().get_max_connections()));
+        json!({ "backend": self.backend(), "size": size, "idle": idle, "max": max })
+    }
+
+    // Cierra las conexiones esperando a que se devuelvan las que están en uso
+    pub async fn close(&self) {
+        with_pool!(&self.pool, |pool| pool.close().await)
+    }
+}
+
+fn backend_name(pool: &DbPool) -> &'static str {
+    match pool {
+        DbPool::Sqlite(_) => "sqlite",
+        DbPool::Postgres(_) => "postgres",
+    }
+}
+
+fn pool_options<DB: sqlx::Database>(config: &DatabaseConfig) -> PoolOptions<DB> {
+    let optional = |secs: u64| (secs > 0).then(|| Duration::from_secs(secs));
+    PoolOptions::new()
+        .max_connections(config.max_connections.max(1))
+        .min_connections(config.min_connections)
+        .idle_timeout(optional(config.idle_timeout_secs))
+        .max_lifetime(optional(config.max_lifetime_secs))
+}
+
+fn acquire_timeout(config: &DatabaseConfig) -> Duration {
+    Duration::from_secs(config.acquire_timeout_secs.max(1))
+}
+
+// No se incluye la URL en el mensaje: puede llevar la contraseña
+fn invalid_url(e: sqlx::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("URL de base de datos inválida: {}", e))
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(e: sqlx::Error) -> Self {
+        match e {
+            sqlx::Error::PoolTimedOut => AppError::ServiceUnavailable("Base de datos no disponible".to_string()),
+            other => AppError::Internal(format!("Error de base de datos: {}", other)),
+        }
+    }
+}
+
+// Comprobación de readiness: la base de datos responde; incluye el estado del pool
+pub struct DatabaseCheck {
+    database: Database,
+}
+
+impl DatabaseCheck {
+    pub fn new(database: Database) -> Self {
+        DatabaseCheck { database }
+    }
+}
+
+impl HealthCheck for DatabaseCheck {
+    fn name(&self) -> &str {
+        "database"
+    }
+
+    fn check(&self) -> BoxFuture<'_, Result<(), String>> {
+        Box::pin(async move { self.database.ping().await.map_err(|e| e.to_string()) })
+    }
+
+    fn details(&self) -> Option<Value> {
+        Some(self.database.pool_status())
+    }
+}
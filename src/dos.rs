@@ -0,0 +1,274 @@
+// dos.rs
+use crate::audit::{self, AuditEvent};
+use crate::client_ip::ClientIp;
+use crate::config::DosConfig;
+use crate::connections;
+use crate::error::AppError;
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{Extensions, Payload, ServiceRequest, ServiceResponse};
+use actix_web::error::PayloadError;
+use actix_web::http::ConnectionType;
+use actix_web::middleware::Next;
+use actix_web::web::Data;
+use actix_web::{Error, HttpMessage, ResponseError};
+use futures_util::{stream, StreamExt};
+use ipnet::IpNet;
+use prometheus::{IntCounter, IntCounterVec, IntGauge, Opts, Registry};
+use socket2::SockRef;
+use std::any::Any;
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, Shutdown};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+// Motivos de infracción; también son la etiqueta de las métricas
+pub const CONNECTION_LIMIT: &str = "connection_limit";
+pub const SLOW_BODY: &str = "slow_body";
+pub const RATE_LIMIT: &str = "rate_limit";
+
+#[derive(Debug, Default)]
+struct ClientState {
+    connections: usize,
+    strikes: Vec<Instant>,
+    banned_until: Option<Instant>,
+}
+
+// Límites por IP de cliente y lista de IP bloqueadas temporalmente
+pub struct DosGuard {
+    config: DosConfig,
+    exempt: Vec<IpNet>,
+    clients: Mutex<HashMap<IpAddr, ClientState>>,
+    strikes: IntCounterVec,
+    slow_requests: IntCounter,
+    bans: IntCounter,
+    banned: IntGauge,
+}
+
+impl DosGuard {
+    // Los proxies de confianza quedan exentos: por ellos llegan muchos clientes distintos
+    pub fn new(config: &DosConfig, trusted_proxies: &[IpNet], registry: &Registry) -> prometheus::Result<Self> {
+        let guard = DosGuard {
+            config: config.clone(),
+            exempt: config.exempt.iter().chain(trusted_proxies).cloned().collect(),
+            clients: Mutex::new(HashMap::new()),
+            strikes: IntCounterVec::new(Opts::new("dos_strikes_total", "Infracciones registradas, por motivo"), &["reason"])?,
+            slow_requests: IntCounter::new("dos_slow_requests_total", "Peticiones cortadas por un cuerpo demasiado lento")?,
+            bans: IntCounter::new("dos_bans_total", "Bloqueos temporales de IP")?,
+            banned: IntGauge::new("dos_banned_ips", "IP bloqueadas en este momento")?,
+        };
+
+        registry.register(Box::new(guard.strikes.clone()))?;
+        registry.register(Box::new(guard.slow_requests.clone()))?;
+        registry.register(Box::new(guard.bans.clone()))?;
+        registry.register(Box::new(guard.banned.clone()))?;
+        Ok(guard)
+    }
+
+    fn is_exempt(&self, ip: IpAddr) -> bool {
+        self.exempt.iter().any(|network| network.contains(&ip))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<IpAddr, ClientState>> {
+        self.clients.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // Tiempo restante de bloqueo de la IP
+    pub fn banned_for(&self, ip: IpAddr) -> Option<Duration> {
+        let now = Instant::now();
+        self.lock()
+            .get(&ip)
+            .and_then(|client| client.banned_until)
+            .and_then(|until| until.checked_duration_since(now))
+    }
+
+    // Registra una infracción; al llegar a `ban_after_strikes` dentro de la ventana se bloquea la IP
+    pub fn strike(&self, ip: IpAddr, reason: &'static str) {
+        if self.is_exempt(ip) {
+            return;
+        }
+        self.strikes.with_label_values(&[reason]).inc();
+        if self.config.ban_after_strikes == 0 {
+            return;
+        }
+
+        let now = Instant::now();
+        let window = Duration::from_secs(self.config.strike_window_secs);
+        let mut clients = self.lock();
+        let client = clients.entry(ip).or_default();
+        client.strikes.retain(|at| now.duration_since(*at) < window);
+        client.strikes.push(now);
+        if client.strikes.len() < self.config.ban_after_strikes as usize || client.banned_until.is_some_and(|until| until > now) {
+            return;
+        }
+
+        let duration = Duration::from_secs(self.config.ban_secs);
+        client.banned_until = Some(now + duration);
+        client.strikes.clear();
+        self.bans.inc();
+        self.banned.inc();
+        audit::record(&AuditEvent::IpBanned { ip, duration, reason });
+    }
+
+    // Callback on_connect: cierra en el acto las conexiones de IP bloqueadas o con
+    // demasiadas conexiones abiertas y devuelve el motivo, que cuenta ConnectionTracker.
+    // actix no permite rechazarlas antes de aceptarlas.
+    pub fn on_connect(guard: &Data<DosGuard>, connection: &dyn Any, data: &mut Extensions) -> Option<&'static str> {
+        let tcp = connections::tcp_stream(connection)?;
+        let ip = tcp.peer_addr().ok()?.ip();
+        if guard.is_exempt(ip) {
+            return None;
+        }
+
+        let reason = {
+            let mut clients = guard.lock();
+            let client = clients.entry(ip).or_default();
+            if client.banned_until.is_some_and(|until| until > Instant::now()) {
+                Some("banned")
+            } else if guard.config.max_connections_per_ip > 0 && client.connections >= guard.config.max_connections_per_ip {
+                Some(CONNECTION_LIMIT)
+            } else {
+                client.connections += 1;
+                None
+            }
+        };
+
+        match reason {
+            None => {
+                data.insert(ClientConnection {
+                    guard: guard.clone(),
+                    ip,
+                });
+            }
+            Some(reason) => {
+                if reason == CONNECTION_LIMIT {
+                    warn!("Límite de conexiones por IP superado: {}", ip);
+                    guard.strike(ip, CONNECTION_LIMIT);
+                }
+                if let Err(e) = SockRef::from(tcp).shutdown(Shutdown::Both) {
+                    warn!("No se pudo cerrar la conexión de {}: {}", ip, e);
+                }
+            }
+        }
+        reason
+    }
+
+    fn release(&self, ip: IpAddr) {
+        let mut clients = self.lock();
+        if let Some(client) = clients.get_mut(&ip) {
+            client.connections = client.connections.saturating_sub(1);
+        }
+    }
+
+    // Elimina los bloqueos caducados y las IP sin conexiones ni infracciones recientes
+    pub fn purge_expired(&self) {
+        let now = Instant::now();
+        let window = Duration::from_secs(self.config.strike_window_secs);
+        let mut clients = self.lock();
+        for client in clients.values_mut() {
+            if client.banned_until.is_some_and(|until| until <= now) {
+                client.banned_until = None;
+            }
+            client.strikes.retain(|at| now.duration_since(*at) < window);
+        }
+        clients.retain(|_, client| client.connections > 0 || !client.strikes.is_empty() || client.banned_until.is_some());
+        self.banned.set(clients.values().filter(|client| client.banned_until.is_some()).count() as i64);
+    }
+}
+
+// Se guarda en los datos de la conexión; al cerrarse deja de contar para su IP
+struct ClientConnection {
+    guard: Data<DosGuard>,
+    ip: IpAddr,
+}
+
+impl Drop for ClientConnection {
+    fn drop(&mut self) {
+        self.guard.release(self.ip);
+    }
+}
+
+// Rechaza las peticiones de clientes bloqueados (con la IP resuelta tras los proxies)
+// y corta los cuerpos que llegan demasiado despacio
+pub async fn dos_middleware(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let Some(guard) = req.app_data::<Data<DosGuard>>().cloned() else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+
+    let client_ip = ClientIp::from_service_request(&req).0;
+    if let Some(remaining) = guard.banned_for(client_ip) {
+        let error = AppError::TooManyRequests {
+            detail: "Cliente bloqueado temporalmente".to_string(),
+            retry_after: Some(remaining.as_secs()),
+        };
+        let mut res = error.error_response();
+        res.head_mut().set_connection_type(ConnectionType::Close);
+        return Ok(req.into_response(res).map_into_right_body());
+    }
+
+    let timed_out = Arc::new(AtomicBool::new(false));
+    let body_timeout = (guard.config.body_timeout_secs > 0).then(|| Duration::from_secs(guard.config.body_timeout_secs));
+    if body_timeout.is_some() || guard.config.min_body_rate_bytes_per_sec > 0 {
+        let payload = slow_body_limit(req.take_payload(), &guard.config, timed_out.clone());
+        req.set_payload(payload);
+    }
+
+    let res = next.call(req).await?;
+    if !timed_out.load(Ordering::Relaxed) {
+        return Ok(res.map_into_left_body());
+    }
+
+    // El handler ya ha respondido al error de lectura; se sustituye por un 408 y se cierra la conexión
+    warn!("Cuerpo demasiado lento de {}", client_ip);
+    guard.slow_requests.inc();
+    guard.strike(client_ip, SLOW_BODY);
+    let (req, _) = res.into_parts();
+    let mut res = AppError::RequestTimeout("El cuerpo de la petición llega demasiado despacio".to_string()).error_response();
+    res.head_mut().set_connection_type(ConnectionType::Close);
+    Ok(ServiceResponse::new(req, res).map_into_right_body())
+}
+
+// Cuerpo con un tiempo máximo total y, pasado el margen inicial, una velocidad mínima:
+// cada fragmento debe llegar antes de que la media caiga por debajo del mínimo
+fn slow_body_limit(payload: Payload, config: &DosConfig, timed_out: Arc<AtomicBool>) -> Payload {
+    let start = Instant::now();
+    let deadline = (config.body_timeout_secs > 0).then(|| start + Duration::from_secs(config.body_timeout_secs));
+    let min_rate = config.min_body_rate_bytes_per_sec;
+    let grace = Duration::from_secs(config.min_rate_grace_secs);
+
+    let body = stream::unfold((payload, 0u64, false), move |(mut payload, received, done)| {
+        let timed_out = timed_out.clone();
+        async move {
+            if done {
+                return None;
+            }
+            let now = Instant::now();
+            let mut limit = deadline;
+            if min_rate > 0 {
+                let rate_limit = start + grace + Duration::from_secs_f64(received as f64 / min_rate as f64);
+                limit = Some(limit.map_or(rate_limit, |deadline| deadline.min(rate_limit)));
+            }
+            let wait = limit.map_or(Duration::MAX, |limit| limit.saturating_duration_since(now));
+
+            match tokio::time::timeout(wait, payload.next()).await {
+                Ok(Some(Ok(chunk))) => {
+                    let received = received + chunk.len() as u64;
+                    Some((Ok(chunk), (payload, received, false)))
+                }
+                Ok(Some(Err(e))) => Some((Err(e), (payload, received, true))),
+                Ok(None) => None,
+                Err(_) => {
+                    timed_out.store(true, Ordering::Relaxed);
+                    let error = io::Error::new(io::ErrorKind::TimedOut, "Cuerpo de la petición demasiado lento");
+                    Some((Err(PayloadError::Io(error)), (payload, received, true)))
+                }
+            }
+        }
+    });
+    Payload::from(body.boxed_local())
+}
@@ -0,0 +1,354 @@
+// error.rs
+use crate::file_cache;
+use crate::logging::RequestId;
+use actix_web::body::{self, BodySize, BoxBody, MessageBody};
+use actix_web::dev::ServiceResponse;
+use actix_web::http::header::{self, Accept, HeaderValue};
+use actix_web::http::StatusCode;
+use actix_web::middleware::{ErrorHandlerResponse, ErrorHandlers};
+use actix_web::{HttpMessage, HttpRequest, HttpResponse, ResponseError};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::{fmt, io};
+use tracing::error;
+
+// Los cuerpos de error más grandes (o en streaming) se dejan como están
+const MAX_RENDERED_BODY: u64 = 64 * 1024;
+const PROBLEM_JSON: &str = "application/problem+json";
+const ERROR_PAGES_DIR: &str = "./static/errors";
+// Lo que ve el cliente de un error interno; el detalle real solo va al log
+const INTERNAL_DETAIL: &str = "Se ha producido un error interno";
+
+// Errores de la aplicación. El texto es el detalle que se muestra al cliente,
+// salvo en Internal, que solo se registra en el log.
+#[derive(Debug)]
+pub enum AppError {
+    BadRequest(String),
+    Unauthorized(String),
+    Forbidden(String),
+    NotFound,
+    RequestTimeout(String),
+    PayloadTooLarge(String),
+    UriTooLong(String),
+    HeadersTooLarge(String),
+    // Datos bien formados pero no válidos, con el motivo de cada campo
+    Validation(Vec<FieldError>),
+    TooManyRequests { detail: String, retry_after: Option<u64> },
+    ServiceUnavailable(String),
+    Internal(String),
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::BadRequest(detail)
+            | AppError::Unauthorized(detail)
+            | AppError::Forbidden(detail)
+            | AppError::RequestTimeout(detail)
+            | AppError::PayloadTooLarge(detail)
+            | AppError::UriTooLong(detail)
+            | AppError::HeadersTooLarge(detail)
+            | AppError::ServiceUnavailable(detail)
+            | AppError::TooManyRequests { detail, .. } => f.write_str(detail),
+            AppError::NotFound => f.write_str("El recurso solicitado no existe"),
+            AppError::Validation(_) => f.write_str("Los datos enviados no son válidos"),
+            AppError::Internal(detail) => write!(f, "Error interno: {}", detail),
+        }
+    }
+}
+
+impl From<io::Error> for AppError {
+    fn from(e: io::Error) -> Self {
+        AppError::Internal(e.to_string())
+    }
+}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::NotFound => StatusCode::NOT_FOUND,
+            AppError::RequestTimeout(_) => StatusCode::REQUEST_TIMEOUT,
+            AppError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            AppError::UriTooLong(_) => StatusCode::URI_TOO_LONG,
+            AppError::HeadersTooLarge(_) => StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE,
+            AppError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
+            AppError::ServiceUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let detail = match self {
+            AppError::Internal(detail) => {
+                error!("{}", detail);
+                INTERNAL_DETAIL.to_string()
+            }
+            other => other.to_string(),
+        };
+
+        let mut res = HttpResponse::build(self.status_code());
+        if let AppError::TooManyRequests { retry_after: Some(secs), .. } = self {
+            res.insert_header((header::RETRY_AFTER, secs.max(&1).to_string()));
+        }
+        let mut problem = Problem::new(self.status_code(), Some(detail));
+        if let AppError::Validation(errors) = self {
+            problem.extensions.insert("errors".to_string(), serde_json::to_value(errors).unwrap_or_default());
+        }
+        res.content_type(PROBLEM_JSON).json(problem)
+    }
+}
+
+// Error de validación de un campo; se devuelve en el miembro "errors" del 422
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, message: impl Into<String>) -> Self {
+        FieldError {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+// Cuerpo de error según RFC 9457 (problem details)
+#[derive(Debug, Serialize)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub title: String,
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    // Miembros de extensión, p. ej. request_id
+    #[serde(flatten)]
+    pub extensions: Map<String, Value>,
+}
+
+impl Problem {
+    pub fn new(status: StatusCode, detail: Option<String>) -> Self {
+        Problem {
+            kind: "about:blank".to_string(),
+            title: status.canonical_reason().unwrap_or("Error").to_string(),
+            status: status.as_u16(),
+            detail,
+            instance: None,
+            extensions: Map::new(),
+        }
+    }
+
+    // Interpreta el cuerpo de una respuesta de error ya generada
+    fn from_body(status: StatusCode, content_type: &str, bytes: &[u8]) -> Self {
+        let mut problem = Problem::new(status, None);
+        if content_type.contains("json") {
+            if let Ok(Value::Object(mut object)) = serde_json::from_slice::<Value>(bytes) {
+                if let Some(Value::String(kind)) = object.remove("type") {
+                    problem.kind = kind;
+                }
+                if let Some(Value::String(title)) = object.remove("title") {
+                    problem.title = title;
+                }
+                object.remove("status");
+                object.remove("instance");
+                problem.detail = ["detail", "error", "message"]
+                    .iter()
+                    .find_map(|key| match object.remove(*key) {
+                        Some(Value::String(detail)) => Some(detail),
+                        _ => None,
+                    });
+                problem.extensions = object;
+            }
+        } else if content_type.is_empty() || content_type.starts_with("text/plain") {
+            let text = String::from_utf8_lossy(bytes);
+            let text = text.trim();
+            problem.detail = (!text.is_empty()).then(|| text.to_string());
+        }
+        problem
+    }
+}
+
+// Middleware que da el mismo formato a todas las respuestas de error (4xx y 5xx),
+// las genere un handler, un middleware o el propio actix
+pub fn error_handlers<B: MessageBody + 'static>() -> ErrorHandlers<B> {
+    ErrorHandlers::new().default_handler(|res: ServiceResponse<B>| {
+        Ok(ErrorHandlerResponse::Future(Box::pin(async move {
+            let res = render_error(res.map_into_boxed_body()).await?;
+            Ok(res.map_into_right_body::<B>())
+        })))
+    })
+}
+
+// JSON (problem details) para la API y los clientes que lo piden; páginas HTML de
+// static/errors/ para los navegadores
+pub async fn render_error(res: ServiceResponse<BoxBody>) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let status = res.status();
+    let renderable = matches!(res.response().body().size(), BodySize::None)
+        || matches!(res.response().body().size(), BodySize::Sized(size) if size <= MAX_RENDERED_BODY);
+    let content_type = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    let context = ErrorContext::new(res.request());
+    // Ya es una página HTML, o un JSON propio del handler (p. ej. el informe de /readyz)
+    let done = if context.wants_html {
+        content_type.starts_with("text/html")
+    } else {
+        content_type.contains("json") && !content_type.starts_with(PROBLEM_JSON)
+    };
+    if !status.is_client_error() && !status.is_server_error() || !renderable || done {
+        return Ok(res);
+    }
+
+    let (req, res) = res.into_parts();
+    let (mut res, body) = res.into_parts();
+    let bytes = body::to_bytes(body).await.map_err(|_| actix_web::error::ErrorInternalServerError(status))?;
+
+    let problem = Problem::from_body(status, &content_type, &bytes);
+    let (content_type, body) = context.render(problem);
+
+    let headers = res.headers_mut();
+    headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    headers.remove(header::CONTENT_LENGTH);
+    headers.remove(header::ETAG);
+    Ok(ServiceResponse::new(req, res.set_body(BoxBody::new(body))))
+}
+
+// Lo que hace falta de la petición para dar formato a un error; se puede obtener
+// antes de pasar la petición al siguiente servicio
+#[derive(Debug, Clone)]
+pub struct ErrorContext {
+    path: String,
+    wants_html: bool,
+    request_id: Option<String>,
+}
+
+impl ErrorContext {
+    pub fn new(req: &HttpRequest) -> Self {
+        ErrorContext {
+            path: req.path().to_string(),
+            wants_html: wants_html(req),
+            request_id: req.extensions().get::<RequestId>().map(|id| id.0.clone()),
+        }
+    }
+
+    fn render(&self, mut problem: Problem) -> (&'static str, String) {
+        problem.instance = Some(self.path.clone());
+        if let Some(request_id) = &self.request_id {
+            problem.extensions.insert("request_id".to_string(), Value::String(request_id.clone()));
+        }
+
+        if self.wants_html {
+            ("text/html; charset=utf-8", error_page(&problem, self.request_id.as_deref()))
+        } else {
+            (PROBLEM_JSON, serde_json::to_string(&problem).unwrap_or_default())
+        }
+    }
+
+    // Respuesta de error completa, para cuando ya no hay petición con la que
+    // construir un ServiceResponse (p. ej. tras un pánico)
+    pub fn into_error(self, error: AppError) -> ContextualError {
+        ContextualError { context: self, error }
+    }
+}
+
+// Error que se devuelve como Err desde un middleware con el mismo formato que el resto
+#[derive(Debug)]
+pub struct ContextualError {
+    context: ErrorContext,
+    error: AppError,
+}
+
+impl fmt::Display for ContextualError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl ResponseError for ContextualError {
+    fn status_code(&self) -> StatusCode {
+        self.error.status_code()
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status_code();
+        let detail = match &self.error {
+            AppError::Internal(_) => INTERNAL_DETAIL.to_string(),
+            other => other.to_string(),
+        };
+        let (content_type, body) = self.context.render(Problem::new(status, Some(detail)));
+        HttpResponse::build(status)
+            .insert_header((header::CACHE_CONTROL, "no-store"))
+            .content_type(content_type)
+            .body(body)
+    }
+}
+
+// HTML solo si el cliente lo prefiere a JSON y no es una ruta de la API
+fn wants_html(req: &HttpRequest) -> bool {
+    if req.path().starts_with("/api/") {
+        return false;
+    }
+    let Some(accept) = req.get_header::<Accept>() else {
+        return false;
+    };
+    accept
+        .ranked()
+        .iter()
+        .find_map(|mime| {
+            if mime.type_() == mime::TEXT && mime.subtype() == mime::HTML {
+                Some(true)
+            } else if mime.subtype() == mime::JSON || mime.suffix() == Some(mime::JSON) {
+                Some(false)
+            } else {
+                None
+            }
+        })
+        .unwrap_or(false)
+}
+
+// Plantilla static/errors/{estado}.html o, si no existe, static/errors/error.html.
+// Marcadores: {{status}}, {{title}}, {{detail}} y {{request_id}}.
+fn error_page(problem: &Problem, request_id: Option<&str>) -> String {
+    let template = file_cache::cached_content(&format!("{}/{}.html", ERROR_PAGES_DIR, problem.status))
+        .or_else(|_| file_cache::cached_content(&format!("{}/error.html", ERROR_PAGES_DIR)))
+        .map(|content| String::from_utf8_lossy(&content).into_owned())
+        .unwrap_or_else(|_| {
+            "<!DOCTYPE html><html lang=\"es\"><head><meta charset=\"UTF-8\"><title>{{status}} {{title}}</title></head>\
+             <body><h1>{{status}} {{title}}</h1><p>{{detail}}</p><p>{{request_id}}</p></body></html>"
+                .to_string()
+        });
+
+    template
+        .replace("{{status}}", &problem.status.to_string())
+        .replace("{{title}}", &escape_html(&problem.title))
+        .replace("{{detail}}", &escape_html(problem.detail.as_deref().unwrap_or("")))
+        .replace("{{request_id}}", &escape_html(request_id.unwrap_or("-")))
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
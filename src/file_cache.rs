@@ -1,17 +1,21 @@
 use actix_web::{HttpResponse};
 use std::collections::HashMap;
+use std::io;
 use std::str::FromStr;
-use std::sync::RwLock;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 use actix_web::http::header::ContentType;
 use lazy_static::lazy_static;
 use mime::Mime;
+use crate::error::AppError;
 use crate::file_utils;
+use tracing::field::Empty;
+use tracing::{debug, error, instrument, warn, Span};
 
 lazy_static! {
-    static ref FILE_CACHE: RwLock<HashMap<String, (String, Vec<u8>)>> = RwLock::new(HashMap::new());
+    static ref FILE_CACHE: RwLock<Cache> = RwLock::new(HashMap::new());
 }
 
-// pub(crate) fn file_handler(file_path: &str) -> HttpResponse {
+// pub fn file_handler(file_path: &str) -> HttpResponse {
 //     let cache = FILE_CACHE.read().unwrap();
 //     if let Some((etag, content)) = cache.get(file_path) {
 //         // Si ya está en caché, devolvemos la respuesta
@@ -32,58 +36,98 @@ lazy_static! {
 //     }
 // }
 
-pub(crate) fn file_handler(file_path: &str) -> HttpResponse {
+#[instrument(skip_all, fields(file = file_path, cache = Empty))]
+pub fn file_handler(file_path: &str) -> Result<HttpResponse, AppError> {
+    let (path, etag, content) = load(file_path)?;
+    Ok(build_response(&etag, &content, &path))
+}
+
+// Contenido del archivo, desde el caché o desde el disco
+pub fn cached_content(file_path: &str) -> io::Result<Vec<u8>> {
+    load(file_path).map(|(_, _, content)| content)
+}
+
+// Ruta normalizada, ETag y contenido del archivo
+fn load(file_path: &str) -> io::Result<(String, String, Vec<u8>)> {
     //use std::fs;
     use std::path::Path;
 
     // Normalizar la ruta para evitar claves inconsistentes en el caché
-    let normalized_path = match Path::new(file_path).canonicalize() {
-        Ok(path) => path,
-        Err(e) => {
-            eprintln!("Error al normalizar la ruta '{}': {}", file_path, e);
-            return HttpResponse::InternalServerError().body(format!("Error al acceder al archivo: {}", e));
-        }
-    };
+    let normalized_path = Path::new(file_path).canonicalize().map_err(|e| {
+        error!("Error al normalizar la ruta '{}': {}", file_path, e);
+        e
+    })?;
 
     let normalized_path_str = match normalized_path.to_str() {
         Some(path_str) => path_str.to_string(),
         None => {
-            eprintln!("Error al convertir la ruta a cadena: {:?}", normalized_path);
-            return HttpResponse::InternalServerError().body("Error al procesar la ruta del archivo");
+            error!("Error al convertir la ruta a cadena: {:?}", normalized_path);
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Ruta no válida en UTF-8"));
         }
     };
 
     // Verificar el caché
-    let cache = FILE_CACHE.read().unwrap();
+    let cache = read_cache();
     if let Some((etag, content)) = cache.get(&normalized_path_str) {
-        println!("Archivo encontrado en caché: {} ({} bytes)", normalized_path_str, content.len());
-        return build_response(etag, content, &normalized_path_str);
+        debug!("Archivo encontrado en caché: {} ({} bytes)", normalized_path_str, content.len());
+        Span::current().record("cache", "hit");
+        return Ok((normalized_path_str, etag.clone(), content.clone()));
     }
     drop(cache); // Liberar el lock antes de escribir en el caché
 
     // Cargar desde el disco si no está en caché
+    Span::current().record("cache", "miss");
     match file_utils::load_file(&normalized_path_str) {
         Ok((etag, content)) => {
             // Insertar en el caché
-            let mut cache = FILE_CACHE.write().unwrap();
+            let mut cache = write_cache();
             cache.insert(normalized_path_str.clone(), (etag.clone(), content.clone()));
-            println!("Archivo cargado y añadido al caché: {}", normalized_path_str);
-            build_response(&etag, &content, &normalized_path_str)
+            debug!("Archivo cargado y añadido al caché: {}", normalized_path_str);
+            Ok((normalized_path_str, etag, content))
         }
         Err(e) => {
-            eprintln!("Error al cargar el archivo '{}': {}", normalized_path_str, e);
-            HttpResponse::InternalServerError().body(format!("Error al cargar el archivo: {}", e))
+            error!("Error al cargar el archivo '{}': {}", normalized_path_str, e);
+            Err(e)
         }
     }
 }
 
 
-fn build_response(etag: &str, content: &[u8], file_path: &str) -> HttpResponse {
+// Vacía el caché para que los archivos se vuelvan a leer del disco
+pub fn clear() {
+    write_cache().clear();
+}
 
-    println!("{:?}, {:?}, {:?}",etag,  content, file_path);
+type Cache = HashMap<String, (String, Vec<u8>)>;
 
+// Un pánico con el lock tomado envenena el RwLock; en lugar de fallar en todas las
+// peticiones siguientes se descarta el contenido (puede estar a medias) y se sigue
+fn read_cache() -> RwLockReadGuard<'static, Cache> {
+    if FILE_CACHE.is_poisoned() {
+        recover_poisoned();
+    }
+    FILE_CACHE.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn write_cache() -> RwLockWriteGuard<'static, Cache> {
+    if FILE_CACHE.is_poisoned() {
+        recover_poisoned();
+    }
+    FILE_CACHE.write().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn recover_poisoned() {
+    let mut cache = FILE_CACHE.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if FILE_CACHE.is_poisoned() {
+        warn!("Caché de archivos envenenado por un pánico; se vacía");
+        cache.clear();
+        FILE_CACHE.clear_poison();
+    }
+}
+
+fn build_response(etag: &str, content: &[u8], file_path: &str) -> HttpResponse {
     // Determinar el tipo de contenido basado en la extensión del archivo
-    let content_type = match file_path.split('.').last() {
+    let content_type = match file_path.rsplit('.').next() {
         Some("html") => ContentType::html(),
         Some("css") => ContentType(mime::TEXT_CSS),
         Some("js") => ContentType(mime::APPLICATION_JAVASCRIPT),
@@ -92,7 +136,6 @@ fn build_response(etag: &str, content: &[u8], file_path: &str) -> HttpResponse {
         Some("svg") => ContentType(Mime::from_str("image/svg+xml").unwrap()),
         _ => ContentType(mime::TEXT_PLAIN),
     };
-    println!("{:?}, {:?}, {:?}",etag,  content, file_path);
 
     HttpResponse::Ok()
         .append_header(("Cache-Control", "max-age=31536000")) // 1 año
@@ -5,7 +5,7 @@ pub(crate) fn generate_etag(vec_file: &Vec<u8>) -> Result<String, io::Error> {
 //Result<String, es tipo String porque se tiene que saber el tamaño al compilar
     let mut hasher = Sha256::new();
 
-    hasher.update(&vec_file);
+    hasher.update(vec_file);
 
     let etag = hasher.finalize();
 
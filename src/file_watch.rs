@@ -0,0 +1,52 @@
+// file_watch.rs
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::info;
+
+// Llama a `on_change` cada vez que cambia alguno de los archivos. Se vigilan sus
+// directorios para detectar también los archivos reemplazados (renombrados encima),
+// y los eventos que llegan durante `settle` se agrupan en una sola llamada.
+pub async fn watch_files<F>(what: &str, files: &[PathBuf], settle: Duration, mut on_change: F) -> io::Result<()>
+where
+    F: FnMut(),
+{
+    let files: HashSet<&PathBuf> = files.iter().collect();
+    let directories: HashSet<PathBuf> = files
+        .iter()
+        .map(|file| file.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new(".")).to_path_buf())
+        .collect();
+
+    let (tx, mut rx) = mpsc::channel(16);
+    let mut watcher = RecommendedWatcher::new(
+        move |res| {
+            if let Ok(event) = res {
+                let _ = tx.try_send(event);
+            }
+        },
+        Default::default(),
+    )
+    .map_err(io::Error::other)?;
+
+    for directory in &directories {
+        info!("Monitoreando {} en '{}'", what, directory.display());
+        watcher.watch(directory, RecursiveMode::NonRecursive).map_err(io::Error::other)?;
+    }
+
+    while let Some(event) = rx.recv().await {
+        let relevant = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_))
+            && event.paths.iter().any(|path| files.iter().any(|file| path.ends_with(file.file_name().unwrap_or_default())));
+        if !relevant {
+            continue;
+        }
+
+        tokio::time::sleep(settle).await;
+        while rx.try_recv().is_ok() {}
+        on_change();
+    }
+
+    Ok(())
+}
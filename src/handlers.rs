@@ -0,0 +1,83 @@
+// handlers.rs
+use crate::error::AppError;
+use crate::file_cache;
+use actix_session::Session;
+use actix_web::{HttpRequest, HttpResponse};
+use std::path::Path;
+use tracing::{debug, warn};
+
+pub async fn static_files(req: HttpRequest) -> Result<HttpResponse, AppError> {
+    let filename = req.match_info().query("filename");
+    let path = format!("./static/{}", filename);
+
+    debug!(
+        "RUTA GENÉRICA: Solicitado: {}, Mapeado a: {}",
+        filename, path
+    );
+
+    // Se resuelve la ruta real (con "..", enlaces, etc.) y se exige que siga dentro de
+    // ./static; si no, para el cliente el archivo simplemente no existe
+    let root = Path::new("./static").canonicalize().map_err(|_| AppError::NotFound)?;
+    match Path::new(&path).canonicalize() {
+        Ok(real) if real.starts_with(&root) && real.is_file() => {
+            file_cache::file_handler(&path)
+        }
+        Ok(real) => {
+            warn!("Ruta fuera de ./static rechazada: {} -> {}", filename, real.display());
+            Err(AppError::NotFound)
+        }
+        Err(_) => Err(AppError::NotFound),
+    }
+}
+
+
+pub async fn index_page(session: Session) -> Result<HttpResponse, AppError> {
+    if session
+        .get::<String>("auth_token")
+        .unwrap_or(None)
+        .is_some()
+    {
+        file_cache::file_handler("./static/index/index.html")
+    } else {
+        Ok(HttpResponse::Found()
+            .append_header(("Location", "/login"))
+            .finish())
+    }
+}
+
+pub async fn index_script() -> Result<HttpResponse, AppError> {
+    file_cache::file_handler("./static/index/index_script.js")
+}
+
+pub async fn login_page() -> Result<HttpResponse, AppError> {
+    let path = "./static/login/login.html"; // Ruta completa al archivo
+
+    // Verificar si el archivo existe
+    if !Path::new(path).is_file() {
+        warn!("Archivo no encontrado: {}", path);
+        return Err(AppError::NotFound);
+    }
+
+    debug!("Sirviendo archivo desde /login: {}", path);
+    file_cache::file_handler(path) // Sirve el archivo
+}
+
+pub async fn login_script() -> Result<HttpResponse, AppError> {
+    file_cache::file_handler("./static/login/login_script.js")
+}
+
+pub async fn allcss_page() -> Result<HttpResponse, AppError> {
+    file_cache::file_handler("./static/all.css")
+}
+
+// Página de error 404
+pub async fn not_found() -> Result<HttpResponse, AppError> {
+    Err(AppError::NotFound)
+}
+
+// fn file_handler() -> HttpResponse {
+//     HttpResponse::Ok()
+//         .append_header(("Cache-Control", "max-age=31536000")) // 1 año
+//         .append_header(("ETag", "custom-etag-value"))
+//         .body("Contenido del archivo estático")
+// }
@@ -0,0 +1,277 @@
+// health.rs
+use crate::lifecycle::TaskStatus;
+use actix_web::web::Data;
+use actix_web::HttpResponse;
+use futures_util::future::{self, BoxFuture};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+// Tiempo máximo de cada comprobación; las que tardan más cuentan como fallidas
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Una comprobación de salud. Devuelve Err con el motivo si el componente no está bien.
+pub trait HealthCheck: Send + Sync {
+    fn name(&self) -> &str;
+    fn check(&self) -> BoxFuture<'_, Result<(), String>>;
+
+    // Datos adicionales para el informe (p. ej. el estado de un pool)
+    fn details(&self) -> Option<Value> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Probe {
+    // Si falla, el proceso no se recupera solo y hay que reiniciarlo
+    Liveness,
+    // Si falla, no se le debe enviar tráfico (arrancando, deteniéndose o sin dependencias)
+    Readiness,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CheckReport {
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub duration_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
+    pub status: &'static str,
+    pub checks: BTreeMap<String, CheckReport>,
+}
+
+impl HealthReport {
+    pub fn is_healthy(&self) -> bool {
+        self.status == "ok"
+    }
+
+    // Sin errores, tiempos ni detalles: pueden revelar rutas, errores de conexión o el
+    // estado de los pools a cualquiera que llegue al listener público
+    pub fn summary(&self) -> Value {
+        let checks: serde_json::Map<String, Value> = self
+            .checks
+            .iter()
+            .map(|(name, check)| (name.clone(), json!({ "status": check.status })))
+            .collect();
+        json!({ "status": self.status, "checks": checks })
+    }
+
+    fn into_response(self, detailed: bool) -> HttpResponse {
+        let mut res = if self.is_healthy() {
+            HttpResponse::Ok()
+        } else {
+            HttpResponse::ServiceUnavailable()
+        };
+        if detailed {
+            res.json(self)
+        } else {
+            res.json(self.summary())
+        }
+    }
+}
+
+fn check_report(result: Result<(), String>, elapsed: Duration, details: Option<Value>) -> CheckReport {
+    CheckReport {
+        status: if result.is_ok() { "ok" } else { "fail" },
+        error: result.err(),
+        duration_ms: elapsed.as_secs_f64() * 1000.0,
+        details,
+    }
+}
+
+// Estado de salud del servidor: arranque, parada y comprobaciones registradas
+#[derive(Default)]
+pub struct Health {
+    started: AtomicBool,
+    draining: AtomicBool,
+    checks: RwLock<Vec<(Probe, Arc<dyn HealthCheck>)>>,
+}
+
+impl Health {
+    pub fn register(&self, probe: Probe, check: Arc<dyn HealthCheck>) {
+        self.checks.write().unwrap_or_else(|poisoned| poisoned.into_inner()).push((probe, check));
+    }
+
+    // Arranque completado: los listeners aceptan conexiones
+    pub fn set_started(&self) {
+        self.started.store(true, Ordering::SeqCst);
+    }
+
+    // Parada en curso: deja de estar listo mientras termina las peticiones
+    pub fn begin_shutdown(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    // Ejecuta en paralelo las comprobaciones de la sonda indicada (todas si es None)
+    pub async fn report(&self, probe: Option<Probe>) -> HealthReport {
+        let checks: Vec<Arc<dyn HealthCheck>> = self
+            .checks
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .filter(|(check_probe, _)| probe.is_none_or(|probe| probe == *check_probe))
+            .map(|(_, check)| check.clone())
+            .collect();
+
+        let results = future::join_all(checks.iter().map(|check| async move {
+            let start = Instant::now();
+            let result = match tokio::time::timeout(CHECK_TIMEOUT, check.check()).await {
+                Ok(result) => result,
+                Err(_) => Err(format!("sin respuesta en {}s", CHECK_TIMEOUT.as_secs())),
+            };
+            (check.name().to_string(), check_report(result, start.elapsed(), check.details()))
+        }))
+        .await;
+        let mut checks: BTreeMap<String, CheckReport> = results.into_iter().collect();
+
+        if probe != Some(Probe::Liveness) {
+            let startup = if self.started.load(Ordering::SeqCst) { Ok(()) } else { Err("arrancando".to_string()) };
+            checks.insert("startup".to_string(), check_report(startup, Duration::ZERO, None));
+            let shutdown = if self.is_draining() { Err("deteniéndose".to_string()) } else { Ok(()) };
+            checks.insert("shutdown".to_string(), check_report(shutdown, Duration::ZERO, None));
+        }
+
+        let healthy = checks.values().all(|check| check.error.is_none());
+        HealthReport {
+            status: if healthy { "ok" } else { "fail" },
+            checks,
+        }
+    }
+}
+
+// GET /healthz: todas las comprobaciones con su detalle (listener de administración)
+pub async fn healthz(health: Data<Health>) -> HttpResponse {
+    health.report(None).await.into_response(true)
+}
+
+// GET /readyz: listo para recibir tráfico
+pub async fn readyz(health: Data<Health>) -> HttpResponse {
+    health.report(Some(Probe::Readiness)).await.into_response(true)
+}
+
+// GET /livez: el proceso funciona y no necesita reiniciarse
+pub async fn livez(health: Data<Health>) -> HttpResponse {
+    health.report(Some(Probe::Liveness)).await.into_response(true)
+}
+
+// Las mismas sondas en el listener público: solo el estado de cada comprobación
+pub async fn public_healthz(health: Data<Health>) -> HttpResponse {
+    health.report(None).await.into_response(false)
+}
+
+pub async fn public_readyz(health: Data<Health>) -> HttpResponse {
+    health.report(Some(Probe::Readiness)).await.into_response(false)
+}
+
+pub async fn public_livez(health: Data<Health>) -> HttpResponse {
+    health.report(Some(Probe::Liveness)).await.into_response(false)
+}
+
+// El directorio existe y se puede listar
+pub struct DirectoryCheck {
+    name: String,
+    path: PathBuf,
+}
+
+impl DirectoryCheck {
+    pub fn new(name: &str, path: impl Into<PathBuf>) -> Self {
+        DirectoryCheck {
+            name: name.to_string(),
+            path: path.into(),
+        }
+    }
+}
+
+impl HealthCheck for DirectoryCheck {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&self) -> BoxFuture<'_, Result<(), String>> {
+        Box::pin(async move {
+            let mut entries = tokio::fs::read_dir(&self.path)
+                .await
+                .map_err(|e| format!("{}: {}", self.path.display(), e))?;
+            entries
+                .next_entry()
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("{}: {}", self.path.display(), e))
+        })
+    }
+}
+
+// La tarea en segundo plano (p. ej. un monitor de archivos) sigue en marcha
+pub struct TaskCheck {
+    name: String,
+    status: TaskStatus,
+}
+
+impl TaskCheck {
+    pub fn new(name: &str, status: TaskStatus) -> Self {
+        TaskCheck {
+            name: name.to_string(),
+            status,
+        }
+    }
+}
+
+impl HealthCheck for TaskCheck {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&self) -> BoxFuture<'_, Result<(), String>> {
+        let result = if self.status.is_running() {
+            Ok(())
+        } else {
+            Err(format!("la tarea '{}' ha terminado", self.status.name()))
+        };
+        Box::pin(future::ready(result))
+    }
+}
+
+// Estado que actualiza otra parte del servidor, p. ej. si la última combinación de CSS funcionó
+pub struct FlagCheck {
+    name: String,
+    ok: AtomicBool,
+    error: String,
+}
+
+impl FlagCheck {
+    pub fn new(name: &str, error: &str) -> Self {
+        FlagCheck {
+            name: name.to_string(),
+            ok: AtomicBool::new(false),
+            error: error.to_string(),
+        }
+    }
+
+    pub fn set(&self, ok: bool) {
+        self.ok.store(ok, Ordering::SeqCst);
+    }
+}
+
+impl HealthCheck for FlagCheck {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&self) -> BoxFuture<'_, Result<(), String>> {
+        let result = if self.ok.load(Ordering::SeqCst) { Ok(()) } else { Err(self.error.clone()) };
+        Box::pin(future::ready(result))
+    }
+}
@@ -0,0 +1,41 @@
+// html_template.rs
+use actix_web::body::{self, BoxBody};
+use actix_web::dev::ServiceResponse;
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::web::Bytes;
+use actix_web::Error;
+
+pub fn is_html(res: &ServiceResponse<BoxBody>) -> bool {
+    res.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/html"))
+}
+
+// Sustituye un marcador en el cuerpo HTML de la respuesta. Si no hay marcador
+// la respuesta se devuelve igual (con el cuerpo ya leído).
+pub async fn replace_placeholder(
+    res: ServiceResponse<BoxBody>,
+    placeholder: &str,
+    value: impl FnOnce() -> Option<String>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let (req, res) = res.into_parts();
+    let (res, body) = res.into_parts();
+    let bytes = body::to_bytes(body)
+        .await
+        .map_err(|_| actix_web::error::ErrorInternalServerError("Error al leer el cuerpo de la respuesta"))?;
+
+    let html = match std::str::from_utf8(&bytes) {
+        Ok(html) if html.contains(placeholder) => match value() {
+            Some(value) => html.replace(placeholder, &value),
+            None => return Ok(ServiceResponse::new(req, res.set_body(BoxBody::new(bytes)))),
+        },
+        _ => return Ok(ServiceResponse::new(req, res.set_body(BoxBody::new(bytes)))),
+    };
+
+    // El cuerpo depende de la petición: ni ETag ni caché compartida
+    let mut res = res.set_body(BoxBody::new(Bytes::from(html)));
+    res.headers_mut().remove(header::ETAG);
+    res.headers_mut().insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    Ok(ServiceResponse::new(req, res))
+}
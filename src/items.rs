@@ -0,0 +1,295 @@
+// items.rs
+use crate::error::{AppError, FieldError};
+use crate::login::Authenticated;
+use crate::repository::ItemRepository;
+use actix_web::dev::Payload;
+use actix_web::http::header;
+use actix_web::web::{self, Data};
+use actix_web::{Error, FromRequest, HttpRequest, HttpResponse};
+use futures_util::future::LocalBoxFuture;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+const MAX_NAME_CHARS: usize = 100;
+const MAX_DESCRIPTION_CHARS: usize = 2000;
+const MAX_PER_PAGE: u32 = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, sqlx::FromRow)]
+pub struct Item {
+    pub id: i64,
+    pub name: String,
+    pub description: String,
+}
+
+// Cuerpo de POST y PUT
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NewItem {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+// Cuerpo de PATCH: solo cambian los campos presentes
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ItemPatch {
+    pub name: Option<String>,
+    pub description: Option<String>,
+}
+
+// Campos de texto que admite el cuerpo de cada petición
+pub trait BodyFields: DeserializeOwned {
+    const FIELDS: &'static [&'static str];
+    const REQUIRED: &'static [&'static str];
+    // En PATCH, null equivale a no enviar el campo
+    const NULLABLE: bool;
+}
+
+impl BodyFields for NewItem {
+    const FIELDS: &'static [&'static str] = &["name", "description"];
+    const REQUIRED: &'static [&'static str] = &["name"];
+    const NULLABLE: bool = false;
+}
+
+impl BodyFields for ItemPatch {
+    const FIELDS: &'static [&'static str] = &["name", "description"];
+    const REQUIRED: &'static [&'static str] = &[];
+    const NULLABLE: bool = true;
+}
+
+// Cuerpo JSON de POST, PUT y PATCH. El JSON mal formado es un 400 (JsonConfig); los
+// campos que faltan, sobran o no son texto, un 422 con el motivo de cada uno.
+pub struct ItemBody<T>(pub T);
+
+impl<T: BodyFields + 'static> FromRequest for ItemBody<T> {
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let json = web::Json::<Value>::from_request(req, payload);
+        Box::pin(async move {
+            let value = json.await?.into_inner();
+            let errors = field_errors::<T>(&value);
+            if !errors.is_empty() {
+                return Err(AppError::Validation(errors).into());
+            }
+            serde_json::from_value(value)
+                .map(ItemBody)
+                .map_err(|e| AppError::Validation(vec![FieldError::new("body", e.to_string())]).into())
+        })
+    }
+}
+
+fn field_errors<T: BodyFields>(value: &Value) -> Vec<FieldError> {
+    let Some(object) = value.as_object() else {
+        return vec![FieldError::new("body", "Debe ser un objeto JSON")];
+    };
+
+    let mut errors: Vec<FieldError> = T::REQUIRED
+        .iter()
+        .filter(|field| !object.contains_key(**field))
+        .map(|field| FieldError::new(field, "Es obligatorio"))
+        .collect();
+    for (field, value) in object {
+        if !T::FIELDS.contains(&field.as_str()) {
+            errors.push(FieldError::new(field, "Campo desconocido"));
+        } else if !(value.is_string() || (T::NULLABLE && value.is_null())) {
+            errors.push(FieldError::new(field, "Debe ser un texto"));
+        }
+    }
+    errors
+}
+
+// Parámetros de GET /api/items. `sort` admite "id", "name" y "-" delante para
+// orden descendente; `q` filtra por nombre o descripción.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ListQuery {
+    pub page: u32,
+    pub per_page: u32,
+    pub sort: String,
+    pub q: Option<String>,
+}
+
+impl Default for ListQuery {
+    fn default() -> Self {
+        ListQuery {
+            page: 1,
+            per_page: 20,
+            sort: "id".to_string(),
+            q: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortField {
+    Id,
+    Name,
+}
+
+// Consulta ya validada
+#[derive(Debug, Clone)]
+pub struct ListParams {
+    pub offset: u64,
+    pub limit: u32,
+    pub sort: SortField,
+    pub descending: bool,
+    pub filter: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ItemPage {
+    pub items: Vec<Item>,
+    pub page: u32,
+    pub per_page: u32,
+    pub total: u64,
+}
+
+impl NewItem {
+    fn validate(mut self) -> Result<Self, AppError> {
+        self.name = self.name.trim().to_string();
+        let mut errors = Vec::new();
+        validate_name(&self.name, &mut errors);
+        validate_description(&self.description, &mut errors);
+        if errors.is_empty() {
+            Ok(self)
+        } else {
+            Err(AppError::Validation(errors))
+        }
+    }
+}
+
+impl ItemPatch {
+    fn validate(mut self) -> Result<Self, AppError> {
+        self.name = self.name.map(|name| name.trim().to_string());
+        let mut errors = Vec::new();
+        if let Some(name) = &self.name {
+            validate_name(name, &mut errors);
+        }
+        if let Some(description) = &self.description {
+            validate_description(description, &mut errors);
+        }
+        if errors.is_empty() {
+            Ok(self)
+        } else {
+            Err(AppError::Validation(errors))
+        }
+    }
+}
+
+impl ListQuery {
+    pub fn validate(&self) -> Result<ListParams, AppError> {
+        let mut errors = Vec::new();
+        if self.page == 0 {
+            errors.push(FieldError::new("page", "Debe ser 1 o mayor"));
+        }
+        if self.per_page == 0 || self.per_page > MAX_PER_PAGE {
+            errors.push(FieldError::new("per_page", format!("Debe estar entre 1 y {}", MAX_PER_PAGE)));
+        }
+        let (descending, field) = match self.sort.strip_prefix('-') {
+            Some(field) => (true, field),
+            None => (false, self.sort.as_str()),
+        };
+        let sort = match field {
+            "id" => SortField::Id,
+            "name" => SortField::Name,
+            _ => {
+                errors.push(FieldError::new("sort", "Valores admitidos: id, name, -id, -name"));
+                SortField::Id
+            }
+        };
+        if !errors.is_empty() {
+            return Err(AppError::Validation(errors));
+        }
+
+        Ok(ListParams {
+            offset: u64::from(self.page - 1) * u64::from(self.per_page),
+            limit: self.per_page,
+            sort,
+            descending,
+            filter: self.q.as_deref().map(str::trim).filter(|q| !q.is_empty()).map(str::to_lowercase),
+        })
+    }
+}
+
+fn validate_name(name: &str, errors: &mut Vec<FieldError>) {
+    if name.is_empty() {
+        errors.push(FieldError::new("name", "Es obligatorio"));
+    } else if name.chars().count() > MAX_NAME_CHARS {
+        errors.push(FieldError::new("name", format!("Máximo {} caracteres", MAX_NAME_CHARS)));
+    }
+}
+
+fn validate_description(description: &str, errors: &mut Vec<FieldError>) {
+    if description.chars().count() > MAX_DESCRIPTION_CHARS {
+        errors.push(FieldError::new("description", format!("Máximo {} caracteres", MAX_DESCRIPTION_CHARS)));
+    }
+}
+
+// GET /api/items
+pub async fn list_items(
+    _: Authenticated,
+    items: Data<dyn ItemRepository>,
+    query: web::Query<ListQuery>,
+) -> Result<HttpResponse, AppError> {
+    let params = query.validate()?;
+    let (items, total) = items.list(&params).await?;
+    Ok(HttpResponse::Ok().json(ItemPage {
+        items,
+        page: query.page,
+        per_page: query.per_page,
+        total,
+    }))
+}
+
+// GET /api/items/{id}
+pub async fn get_item(_: Authenticated, items: Data<dyn ItemRepository>, id: web::Path<i64>) -> Result<HttpResponse, AppError> {
+    let item = items.get(*id).await?.ok_or(AppError::NotFound)?;
+    Ok(HttpResponse::Ok().json(item))
+}
+
+// POST /api/items
+pub async fn create_item(
+    _: Authenticated,
+    items: Data<dyn ItemRepository>,
+    ItemBody(body): ItemBody<NewItem>,
+) -> Result<HttpResponse, AppError> {
+    let item = items.create(body.validate()?).await?;
+    Ok(HttpResponse::Created()
+        .insert_header((header::LOCATION, format!("/api/items/{}", item.id)))
+        .json(item))
+}
+
+// PUT /api/items/{id}
+pub async fn replace_item(
+    _: Authenticated,
+    items: Data<dyn ItemRepository>,
+    id: web::Path<i64>,
+    ItemBody(body): ItemBody<NewItem>,
+) -> Result<HttpResponse, AppError> {
+    let item = items.replace(*id, body.validate()?).await?.ok_or(AppError::NotFound)?;
+    Ok(HttpResponse::Ok().json(item))
+}
+
+// PATCH /api/items/{id}
+pub async fn update_item(
+    _: Authenticated,
+    items: Data<dyn ItemRepository>,
+    id: web::Path<i64>,
+    ItemBody(body): ItemBody<ItemPatch>,
+) -> Result<HttpResponse, AppError> {
+    let item = items.update(*id, body.validate()?).await?.ok_or(AppError::NotFound)?;
+    Ok(HttpResponse::Ok().json(item))
+}
+
+// DELETE /api/items/{id}
+pub async fn delete_item(_: Authenticated, items: Data<dyn ItemRepository>, id: web::Path<i64>) -> Result<HttpResponse, AppError> {
+    if items.delete(*id).await? {
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Err(AppError::NotFound)
+    }
+}
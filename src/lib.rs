@@ -0,0 +1,37 @@
+pub mod access;
+pub mod admin;
+pub mod app;
+pub mod audit;
+pub mod client_ip;
+pub mod config;
+pub mod connections;
+pub mod cors;
+pub mod css_utils;
+pub mod csrf;
+pub mod db;
+pub mod dos;
+pub mod error;
+pub mod file_cache;
+pub mod file_watch;
+pub mod file_utils;
+pub mod handlers;
+pub mod health;
+pub mod html_template;
+pub mod items;
+pub mod lifecycle;
+pub mod logging;
+pub mod limits;
+pub mod login;
+pub mod metrics;
+pub mod migrations;
+pub mod mtls;
+pub mod paths;
+pub mod rate_limit;
+pub mod recovery;
+pub mod redirects;
+pub mod repository;
+pub mod response_headers;
+pub mod security_headers;
+pub mod server;
+pub mod telemetry;
+pub mod tls;
@@ -0,0 +1,219 @@
+// lifecycle.rs
+use crate::config::Config;
+use crate::health::FlagCheck;
+use crate::access::AccessControl;
+use crate::redirects::Redirects;
+use crate::tls::CertStore;
+use crate::{css_utils, file_cache};
+use std::future::Future;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlSignal {
+    // SIGTERM / SIGINT: dejar de aceptar conexiones y terminar las peticiones en curso
+    Shutdown,
+    // SIGHUP: recargar redirecciones, listas de acceso, certificados y cachés
+    Reload,
+}
+
+// Señales del sistema que controlan el ciclo de vida del servidor
+pub struct Signals {
+    #[cfg(unix)]
+    terminate: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    interrupt: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    hangup: tokio::signal::unix::Signal,
+}
+
+impl Signals {
+    #[cfg(unix)]
+    pub fn new() -> io::Result<Self> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        Ok(Signals {
+            terminate: signal(SignalKind::terminate())?,
+            interrupt: signal(SignalKind::interrupt())?,
+            hangup: signal(SignalKind::hangup())?,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn new() -> io::Result<Self> {
+        Ok(Signals {})
+    }
+
+    #[cfg(unix)]
+    pub async fn recv(&mut self) -> ControlSignal {
+        tokio::select! {
+            _ = self.terminate.recv() => ControlSignal::Shutdown,
+            _ = self.interrupt.recv() => ControlSignal::Shutdown,
+            _ = self.hangup.recv() => ControlSignal::Reload,
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub async fn recv(&mut self) -> ControlSignal {
+        let _ = tokio::signal::ctrl_c().await;
+        ControlSignal::Shutdown
+    }
+}
+
+// Partes de la configuración que se aplican al recargar
+const RELOADABLE: &[&str] = &["redirects", "access", "tls.certificates"];
+
+// Secciones con cambios que solo se aplican reiniciando
+pub fn restart_required(running: &toml::Table, loaded: &toml::Table) -> Vec<String> {
+    let strip = |table: &toml::Table| {
+        let mut table = table.clone();
+        for path in RELOADABLE {
+            match path.split_once('.') {
+                Some((section, key)) => {
+                    if let Some(toml::Value::Table(values)) = table.get_mut(section) {
+                        values.remove(key);
+                        if values.is_empty() {
+                            table.remove(section);
+                        }
+                    }
+                }
+                None => {
+                    table.remove(*path);
+                }
+            }
+        }
+        table
+    };
+    let (running, loaded) = (strip(running), strip(loaded));
+
+    let mut changed: Vec<String> = running
+        .keys()
+        .chain(loaded.keys())
+        .filter(|section| running.get(*section) != loaded.get(*section))
+        .cloned()
+        .collect();
+    changed.sort();
+    changed.dedup();
+    changed
+}
+
+// Lo que se puede recargar sin reiniciar. Los cambios en el resto de la configuración
+// (listeners, límites, cabeceras…) requieren arrancar un proceso nuevo (con
+// reuse_port, sin cortes); al recargar se avisa de cuáles han cambiado.
+pub struct Reloader {
+    pub config_path: String,
+    // Configuración con la que arrancó el proceso
+    pub running_config: toml::Table,
+    pub cert_store: Option<Arc<CertStore>>,
+    pub redirects: Arc<Redirects>,
+    pub access: Arc<AccessControl>,
+    pub css_dir: String,
+    pub css_output: String,
+    // Comprobación de salud con el resultado de la última combinación de CSS
+    pub css_check: Arc<FlagCheck>,
+}
+
+impl Reloader {
+    pub async fn reload(&self) {
+        info!("Recargando configuración desde '{}'", self.config_path);
+        if !Config::exists(&self.config_path) {
+            warn!("Archivo de configuración '{}' no encontrado, se recargan los valores por defecto", self.config_path);
+        }
+
+        match Config::load(&self.config_path) {
+            Ok(config) => {
+                match Config::load_table(&self.config_path).map(|loaded| restart_required(&self.running_config, &loaded)) {
+                    Ok(changed) if !changed.is_empty() => warn!(
+                        "Cambios que no se aplican hasta reiniciar en: {}",
+                        changed.join(", ")
+                    ),
+                    Ok(_) => {}
+                    Err(e) => warn!("No se pudo comparar la configuración: {}", e),
+                }
+                if let Err(e) = self.redirects.replace(&config.redirects) {
+                    error!("Error al recargar las redirecciones (se mantienen las anteriores): {}", e);
+                }
+                if let Err(e) = self.access.replace(&config.access) {
+                    error!("Error al recargar las listas de acceso (se mantienen las anteriores): {}", e);
+                }
+                if let Some(store) = &self.cert_store {
+                    if let Err(e) = store.replace(&config.tls.certificates) {
+                        error!("Error al recargar los certificados TLS (se mantienen los anteriores): {}", e);
+                    }
+                }
+            }
+            Err(e) => warn!("Configuración inválida, se mantiene la anterior: {}", e),
+        }
+
+        file_cache::clear();
+        match css_utils::combine_css(&self.css_dir, &self.css_output).await {
+            Ok(()) => self.css_check.set(true),
+            Err(e) => {
+                error!("Error al combinar CSS: {}", e);
+                self.css_check.set(false);
+            }
+        }
+        info!("Recarga completada");
+    }
+}
+
+// Indica si una tarea en segundo plano sigue en marcha
+#[derive(Clone)]
+pub struct TaskStatus {
+    name: &'static str,
+    running: Arc<AtomicBool>,
+}
+
+impl TaskStatus {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+}
+
+// Tareas en segundo plano (monitores, limpieza) que se cancelan juntas al detener el servidor
+#[derive(Default)]
+pub struct BackgroundTasks {
+    token: CancellationToken,
+    tasks: Vec<(&'static str, JoinHandle<()>)>,
+}
+
+impl BackgroundTasks {
+    pub fn spawn<F>(&mut self, name: &'static str, task: F) -> TaskStatus
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let status = TaskStatus {
+            name,
+            running: Arc::new(AtomicBool::new(true)),
+        };
+        let token = self.token.clone();
+        let running = status.running.clone();
+        let handle = tokio::spawn(async move {
+            tokio::select! {
+                _ = token.cancelled() => {}
+                _ = task => warn!("La tarea '{}' ha terminado", name),
+            }
+            running.store(false, Ordering::SeqCst);
+        });
+        self.tasks.push((name, handle));
+        status
+    }
+
+    pub async fn shutdown(self, timeout: Duration) {
+        self.token.cancel();
+        for (name, task) in self.tasks {
+            if tokio::time::timeout(timeout, task).await.is_err() {
+                warn!("La tarea '{}' no terminó a tiempo", name);
+            }
+        }
+    }
+}
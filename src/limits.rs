@@ -0,0 +1,129 @@
+// limits.rs
+use crate::config::LimitsConfig;
+use crate::error::AppError;
+use crate::paths;
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::error::PayloadError;
+use actix_web::http::header;
+use actix_web::middleware::Next;
+use actix_web::web::Data;
+use actix_web::{Error, HttpMessage, ResponseError};
+use futures_util::StreamExt;
+use tracing::warn;
+
+// Límites de tamaño ya resueltos a partir de la configuración
+pub struct RequestLimits {
+    max_uri_length: usize,
+    max_header_bytes: usize,
+    max_header_count: usize,
+    max_body_bytes: usize,
+    routes: Vec<BodyLimitRule>,
+    pub json_limit_bytes: usize,
+    pub form_limit_bytes: usize,
+}
+
+struct BodyLimitRule {
+    path_prefix: String,
+    content_type: Option<String>,
+    max_body_bytes: usize,
+}
+
+impl RequestLimits {
+    pub fn new(config: &LimitsConfig) -> Self {
+        let mut routes: Vec<_> = config
+            .routes
+            .iter()
+            .map(|route| BodyLimitRule {
+                path_prefix: route.path_prefix.clone(),
+                content_type: route.content_type.as_ref().map(|content_type| content_type.to_ascii_lowercase()),
+                max_body_bytes: route.max_body_bytes,
+            })
+            .collect();
+        // El prefijo más largo tiene prioridad y, con el mismo prefijo, la regla con tipo de contenido
+        routes.sort_by_key(|rule| std::cmp::Reverse((rule.path_prefix.len(), rule.content_type.is_some())));
+
+        RequestLimits {
+            max_uri_length: config.max_uri_length,
+            max_header_bytes: config.max_header_bytes,
+            max_header_count: config.max_header_count,
+            max_body_bytes: config.max_body_bytes,
+            routes,
+            json_limit_bytes: config.json_limit_bytes,
+            form_limit_bytes: config.form_limit_bytes,
+        }
+    }
+
+    // Límite más alto de todas las reglas; el de los extractores de cuerpo genéricos
+    // (Bytes, String), ya que el límite de cada ruta lo aplica el middleware
+    pub fn largest_body_limit(&self) -> usize {
+        self.routes.iter().map(|rule| rule.max_body_bytes).fold(self.max_body_bytes, usize::max)
+    }
+
+    pub fn body_limit_for(&self, path: &str, content_type: &str) -> usize {
+        self.routes
+            .iter()
+            .find(|rule| {
+                paths::matches_prefix(path, &rule.path_prefix)
+                    && rule.content_type.as_deref().is_none_or(|expected| expected == content_type)
+            })
+            .map(|rule| rule.max_body_bytes)
+            .unwrap_or(self.max_body_bytes)
+    }
+}
+
+pub async fn limits_middleware(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let Some(limits) = req.app_data::<Data<RequestLimits>>().cloned() else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+
+    let uri_length = req.uri().path_and_query().map_or(0, |path_and_query| path_and_query.as_str().len());
+    if uri_length > limits.max_uri_length {
+        warn!("URI de {} bytes rechazada (máximo {})", uri_length, limits.max_uri_length);
+        let error = AppError::UriTooLong(format!("La URL supera el máximo de {} bytes", limits.max_uri_length));
+        return Ok(req.into_response(error.error_response()).map_into_right_body());
+    }
+
+    let header_count = req.headers().len();
+    // Tamaño aproximado en la petición: "nombre: valor\r\n"
+    let header_bytes: usize = req.headers().iter().map(|(name, value)| name.as_str().len() + value.len() + 4).sum();
+    if header_count > limits.max_header_count || header_bytes > limits.max_header_bytes {
+        warn!("Cabeceras rechazadas: {} cabeceras, {} bytes", header_count, header_bytes);
+        let error = AppError::HeadersTooLarge(format!(
+            "Las cabeceras superan el máximo de {} cabeceras o {} bytes",
+            limits.max_header_count, limits.max_header_bytes
+        ));
+        return Ok(req.into_response(error.error_response()).map_into_right_body());
+    }
+
+    let body_limit = limits.body_limit_for(paths::route_path(&req), &req.content_type().to_ascii_lowercase());
+    let content_length = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    if content_length.is_some_and(|length| length > body_limit) {
+        warn!("Cuerpo de {} bytes rechazado en {} (máximo {})", content_length.unwrap_or(0), req.path(), body_limit);
+        let error = AppError::PayloadTooLarge(format!("El cuerpo supera el máximo de {} bytes", body_limit));
+        return Ok(req.into_response(error.error_response()).map_into_right_body());
+    }
+
+    // Sin Content-Length (chunked) el límite se comprueba mientras se lee el cuerpo;
+    // los extractores convierten el desbordamiento en un 413
+    let mut received = 0;
+    let payload = req.take_payload().map(move |chunk| {
+        let chunk = chunk?;
+        received += chunk.len();
+        if received > body_limit {
+            Err(PayloadError::Overflow)
+        } else {
+            Ok(chunk)
+        }
+    });
+    req.set_payload(Payload::from(payload.boxed_local()));
+
+    Ok(next.call(req).await?.map_into_left_body())
+}
@@ -0,0 +1,335 @@
+// logging.rs
+use crate::client_ip::ClientIp;
+use crate::config::{AccessLogFormat, LogFormat, LoggingConfig};
+use crate::telemetry::{self, Telemetry};
+use actix_web::body::{self, BodySize, BoxBody, MessageBody};
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::web::Data;
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest};
+use std::fmt;
+use std::future::{ready, Ready};
+use std::io;
+use std::time::{Duration, Instant};
+use time::format_description::well_known::Rfc3339;
+use time::macros::format_description;
+use time::OffsetDateTime;
+use tracing::field::Empty;
+use tracing::{error, info, info_span, warn, Instrument, Level};
+use tracing_subscriber::filter::{EnvFilter, Targets};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt as tracing_fmt, Layer, Registry};
+
+// Tamaño máximo de un cuerpo de error al que se le añade el ID de petición
+const MAX_ANNOTATED_BODY: u64 = 64 * 1024;
+
+// Instala el subscriber global: logs de la aplicación (texto o JSON) con niveles
+// por módulo, aparte las líneas de acceso (target "access") tal cual y, si está
+// activada, la exportación de los spans del servidor a OpenTelemetry.
+pub fn init(config: &LoggingConfig, telemetry: &Telemetry) -> io::Result<()> {
+    let filter = match EnvFilter::try_from_default_env() {
+        Ok(filter) => filter,
+        Err(_) => EnvFilter::try_new(filter_directives(config))
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Niveles de log inválidos: {}", e)))?,
+    };
+    let filter = filter.add_directive("access=off".parse().expect("directiva válida"));
+
+    let app_layer: Box<dyn Layer<Registry> + Send + Sync> = match config.format {
+        LogFormat::Text => tracing_fmt::layer().with_filter(filter).boxed(),
+        LogFormat::Json => tracing_fmt::layer().json().with_filter(filter).boxed(),
+    };
+
+    let access_layer = (config.access_log != AccessLogFormat::Off).then(|| {
+        tracing_fmt::layer()
+            .without_time()
+            .with_level(false)
+            .with_target(false)
+            .with_ansi(false)
+            .with_filter(Targets::new().with_target("access", Level::INFO))
+    });
+
+    // Solo los spans propios: los de las dependencias (incluido el cliente HTTP del
+    // exportador) generarían trazas sin interés o en bucle
+    let otel_layer = telemetry.tracer().map(|tracer| {
+        tracing_opentelemetry::layer()
+            .with_tracer(tracer)
+            .with_filter(Targets::new().with_target("servidor", Level::INFO))
+    });
+
+    tracing_subscriber::registry()
+        .with(app_layer)
+        .with(access_layer)
+        .with(otel_layer)
+        .try_init()
+        .map_err(io::Error::other)
+}
+
+// "info,servidor::tls=debug,actix_server=warn"
+fn filter_directives(config: &LoggingConfig) -> String {
+    std::iter::once(config.level.clone())
+        .chain(config.modules.iter().map(|(module, level)| format!("{}={}", module, level)))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// ID de la petición actual, recibido o generado
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromRequest for RequestId {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let id = req
+            .extensions()
+            .get::<RequestId>()
+            .cloned()
+            .unwrap_or_else(|| RequestId("-".to_string()));
+        ready(Ok(id))
+    }
+}
+
+pub struct RequestLogging {
+    header: HeaderName,
+    trust_incoming: bool,
+    access_log: AccessLogFormat,
+}
+
+impl RequestLogging {
+    pub fn new(config: &LoggingConfig) -> Self {
+        let header = HeaderName::try_from(config.request_id_header.as_str()).unwrap_or_else(|e| {
+            warn!("Cabecera de ID de petición inválida {:?}: {}", config.request_id_header, e);
+            HeaderName::from_static("x-request-id")
+        });
+
+        RequestLogging {
+            header,
+            trust_incoming: config.trust_request_id,
+            access_log: config.access_log,
+        }
+    }
+
+    fn request_id_for(&self, headers: &HeaderMap) -> String {
+        let incoming = headers
+            .get(&self.header)
+            .and_then(|value| value.to_str().ok())
+            .filter(|id| self.trust_incoming && is_valid_request_id(id));
+
+        match incoming {
+            Some(id) => id.to_string(),
+            None => uuid::Uuid::new_v4().to_string(),
+        }
+    }
+}
+
+// Se limita para que un cliente no pueda inyectar texto arbitrario en los logs
+fn is_valid_request_id(id: &str) -> bool {
+    (1..=128).contains(&id.len())
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+}
+
+// Datos de la petición para la línea de acceso
+struct AccessEntry {
+    remote: String,
+    method: String,
+    target: String,
+    protocol: String,
+    referer: Option<String>,
+    user_agent: Option<String>,
+}
+
+impl AccessEntry {
+    fn from_request(req: &ServiceRequest) -> Self {
+        let header = |name| {
+            req.headers()
+                .get(name)
+                .and_then(|value: &HeaderValue| value.to_str().ok())
+                .map(str::to_string)
+        };
+
+        AccessEntry {
+            remote: ClientIp::from_service_request(req).to_string(),
+            method: req.method().to_string(),
+            target: req.uri().path_and_query().map(|pq| pq.as_str()).unwrap_or("/").to_string(),
+            protocol: format!("{:?}", req.version()),
+            referer: header(header::REFERER),
+            user_agent: header(header::USER_AGENT),
+        }
+    }
+
+    fn format(&self, format: AccessLogFormat, status: u16, bytes: Option<u64>, elapsed: Duration, request_id: &str) -> Option<String> {
+        let now = OffsetDateTime::now_utc();
+        let clf_time = || {
+            now.format(format_description!("[day]/[month repr:short]/[year]:[hour]:[minute]:[second] +0000"))
+                .unwrap_or_default()
+        };
+        let bytes_field = bytes.map(|b| b.to_string()).unwrap_or_else(|| "-".to_string());
+
+        match format {
+            AccessLogFormat::Off => None,
+            AccessLogFormat::Common => Some(format!(
+                "{} - - [{}] \"{} {} {}\" {} {}",
+                self.remote, clf_time(), self.method, self.target, self.protocol, status, bytes_field
+            )),
+            AccessLogFormat::Combined => Some(format!(
+                "{} - - [{}] \"{} {} {}\" {} {} \"{}\" \"{}\"",
+                self.remote,
+                clf_time(),
+                self.method,
+                self.target,
+                self.protocol,
+                status,
+                bytes_field,
+                self.referer.as_deref().unwrap_or("-").replace('"', "\\\""),
+                self.user_agent.as_deref().unwrap_or("-").replace('"', "\\\""),
+            )),
+            AccessLogFormat::Json => Some(
+                serde_json::json!({
+                    "time": now.format(&Rfc3339).unwrap_or_default(),
+                    "request_id": request_id,
+                    "remote": self.remote,
+                    "method": self.method,
+                    "target": self.target,
+                    "protocol": self.protocol,
+                    "status": status,
+                    "bytes": bytes,
+                    "duration_ms": elapsed.as_secs_f64() * 1000.0,
+                    "referer": self.referer,
+                    "user_agent": self.user_agent,
+                })
+                .to_string(),
+            ),
+        }
+    }
+}
+
+// Un span por petición con su ID, cabecera X-Request-Id en la respuesta, el ID
+// en los cuerpos de error y la línea de acceso al terminar
+pub async fn request_logging_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let Some(logging) = req.app_data::<Data<RequestLogging>>().cloned() else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+
+    let request_id = logging.request_id_for(req.headers());
+    req.extensions_mut().insert(RequestId(request_id.clone()));
+
+    let span = info_span!(
+        "request",
+        request_id = %request_id,
+        method = %req.method(),
+        path = %req.path(),
+        otel.name = %req.method(),
+        otel.kind = "server",
+        otel.status_code = Empty,
+        http.route = Empty,
+        http.response.status_code = Empty,
+    );
+    telemetry::set_remote_parent(&span, req.headers());
+    let entry = AccessEntry::from_request(&req);
+    let start = Instant::now();
+
+    // Los errores de los handlers ya llegan convertidos en respuesta; aquí solo
+    // llegan los de otros middlewares, que actix convertirá después
+    let res = match next.call(req).instrument(span.clone()).await {
+        Ok(res) => res.map_into_boxed_body(),
+        Err(e) => {
+            let status = e.as_response_error().status_code();
+            span.record("http.response.status_code", status.as_u16());
+            if status.is_server_error() {
+                span.record("otel.status_code", "ERROR");
+                error!(parent: &span, "Error al procesar la petición: {}", e);
+            }
+            if let Some(line) = entry.format(logging.access_log, status.as_u16(), None, start.elapsed(), &request_id) {
+                info!(target: "access", "{}", line);
+            }
+            return Err(e);
+        }
+    };
+    let elapsed = start.elapsed();
+
+    // Nombre del span con el patrón de la ruta, no con la ruta concreta
+    if let Some(route) = res.request().match_pattern() {
+        telemetry::set_span_name(&span, format!("{} {}", res.request().method(), route));
+        span.record("http.route", route);
+    }
+    span.record("http.response.status_code", res.status().as_u16());
+    if res.status().is_server_error() {
+        span.record("otel.status_code", "ERROR");
+    }
+
+    let mut res = if res.status().is_client_error() || res.status().is_server_error() {
+        annotate_error_body(res, &request_id).await?
+    } else {
+        res
+    };
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        res.headers_mut().insert(logging.header.clone(), value);
+    }
+    telemetry::inject_context(&span, res.headers_mut());
+
+    let bytes = match res.response().body().size() {
+        BodySize::Sized(size) => Some(size),
+        BodySize::None => Some(0),
+        BodySize::Stream => None,
+    };
+    if let Some(line) = entry.format(logging.access_log, res.status().as_u16(), bytes, elapsed, &request_id) {
+        info!(target: "access", "{}", line);
+    }
+
+    Ok(res)
+}
+
+// Añade el ID de petición a los cuerpos de error JSON ("request_id"), de texto y HTML ({{request_id}})
+async fn annotate_error_body(res: ServiceResponse<BoxBody>, request_id: &str) -> Result<ServiceResponse<BoxBody>, Error> {
+    let annotatable = matches!(res.response().body().size(), BodySize::Sized(size) if size <= MAX_ANNOTATED_BODY);
+    let content_type = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("text/plain")
+        .to_ascii_lowercase();
+    let is_json = content_type.starts_with("application/json") || content_type.contains("+json");
+    let is_text = content_type.starts_with("text/plain");
+    let is_html = content_type.starts_with("text/html");
+    if !annotatable || !(is_json || is_text || is_html) {
+        return Ok(res);
+    }
+
+    let (req, res) = res.into_parts();
+    let (mut res, body) = res.into_parts();
+    let bytes = body::to_bytes(body).await.map_err(actix_web::error::ErrorInternalServerError)?;
+    let text = String::from_utf8_lossy(&bytes);
+
+    let annotated = if is_json {
+        match serde_json::from_slice::<serde_json::Value>(&bytes) {
+            // Ya lo incluye (p. ej. los problem details): se deja tal cual
+            Ok(serde_json::Value::Object(object)) if object.contains_key("request_id") => text.into_owned(),
+            Ok(serde_json::Value::Object(mut object)) => {
+                object.insert("request_id".to_string(), serde_json::Value::String(request_id.to_string()));
+                serde_json::Value::Object(object).to_string()
+            }
+            _ => text.into_owned(),
+        }
+    } else if is_html {
+        text.replace("{{request_id}}", request_id)
+    } else if text.is_empty() {
+        format!("ID de petición: {}", request_id)
+    } else {
+        format!("{}\nID de petición: {}", text.trim_end(), request_id)
+    };
+
+    res.headers_mut().remove(header::CONTENT_LENGTH);
+    Ok(ServiceResponse::new(req, res.set_body(BoxBody::new(annotated))))
+}
@@ -0,0 +1,306 @@
+// login.rs
+use crate::audit::{self, AuditEvent};
+use crate::client_ip::ClientIp;
+use crate::config::{LoginConfig, UserCredentials};
+use crate::error::AppError;
+use actix_session::{Session, SessionExt};
+use actix_web::dev::Payload;
+use actix_web::http::header;
+use actix_web::{web, FromRequest, HttpRequest, HttpResponse, ResponseError};
+use argon2::password_hash::SaltString;
+use argon2::{Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier};
+use rand::distributions::Alphanumeric;
+use rand::rngs::OsRng;
+use rand::Rng;
+use serde::Deserialize;
+use subtle::ConstantTimeEq;
+use std::collections::HashMap;
+use std::future::{ready, Ready};
+use std::hash::Hash;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::field::Empty;
+use tracing::{info_span, instrument, warn, Span};
+
+// Mensaje único para cualquier fallo, exista o no el usuario
+const INVALID_CREDENTIALS: &str = "Usuario o contraseña incorrectos";
+
+#[derive(Debug, Deserialize)]
+pub struct LoginRequest {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Attempts {
+    failures: u32,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+}
+
+// Seguimiento de intentos fallidos por usuario y por IP con bloqueo exponencial
+pub struct LoginGuard {
+    config: LoginConfig,
+    by_user: Mutex<HashMap<String, Attempts>>,
+    by_ip: Mutex<HashMap<IpAddr, Attempts>>,
+}
+
+impl LoginGuard {
+    pub fn new(config: LoginConfig) -> Self {
+        LoginGuard {
+            config,
+            by_user: Mutex::new(HashMap::new()),
+            by_ip: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Tiempo restante de bloqueo para el usuario o la IP, si alguno está bloqueado
+    pub fn locked_for(&self, username: &str, ip: IpAddr) -> Option<Duration> {
+        let now = Instant::now();
+        let remaining = |attempts: Option<&Attempts>| {
+            attempts
+                .and_then(|a| a.locked_until)
+                .and_then(|until| until.checked_duration_since(now))
+        };
+
+        let user = remaining(lock(&self.by_user).get(username));
+        let ip = remaining(lock(&self.by_ip).get(&ip));
+        user.max(ip)
+    }
+
+    pub fn record_failure(&self, username: &str, ip: IpAddr) {
+        let now = Instant::now();
+
+        if let Some(duration) = self.register_failure(&self.by_user, username.to_string(), self.config.max_failures_per_user, now) {
+            audit::record(&AuditEvent::UserLocked { username, ip, duration });
+        }
+        if let Some(duration) = self.register_failure(&self.by_ip, ip, self.config.max_failures_per_ip, now) {
+            audit::record(&AuditEvent::IpLocked { ip, duration });
+        }
+    }
+
+    // Un acceso correcto limpia el historial del usuario, pero no el de la IP
+    pub fn record_success(&self, username: &str) {
+        lock(&self.by_user).remove(username);
+    }
+
+    // Elimina las entradas sin bloqueo activo cuyos fallos ya caducaron
+    pub fn purge_expired(&self) {
+        let now = Instant::now();
+        let window = Duration::from_secs(self.config.failure_window_secs);
+        let keep = |a: &Attempts| {
+            a.locked_until.is_some_and(|until| until > now) || now.duration_since(a.last_failure) < window
+        };
+        lock(&self.by_user).retain(|_, a| keep(a));
+        lock(&self.by_ip).retain(|_, a| keep(a));
+    }
+
+    pub fn min_response(&self) -> Duration {
+        Duration::from_millis(self.config.min_response_ms)
+    }
+
+    // Registra un fallo y devuelve la duración del bloqueo si se ha aplicado uno
+    fn register_failure<K: Eq + Hash>(
+        &self,
+        map: &Mutex<HashMap<K, Attempts>>,
+        key: K,
+        threshold: u32,
+        now: Instant,
+    ) -> Option<Duration> {
+        let window = Duration::from_secs(self.config.failure_window_secs);
+        let mut map = lock(map);
+        let attempts = map.entry(key).or_insert(Attempts {
+            failures: 0,
+            last_failure: now,
+            locked_until: None,
+        });
+
+        if now.duration_since(attempts.last_failure) >= window {
+            attempts.failures = 0;
+        }
+        attempts.failures += 1;
+        attempts.last_failure = now;
+
+        if threshold == 0 || attempts.failures < threshold {
+            return None;
+        }
+
+        let exponent = (attempts.failures - threshold).min(31);
+        let duration = Duration::from_secs(
+            self.config
+                .base_lockout_secs
+                .saturating_mul(1u64 << exponent)
+                .min(self.config.max_lockout_secs),
+        );
+        attempts.locked_until = Some(now + duration);
+        Some(duration)
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// Usuarios configurados con sus hashes Argon2
+pub struct UserStore {
+    users: HashMap<String, String>,
+    // Hash de relleno para que un usuario inexistente cueste lo mismo que uno real
+    dummy_hash: String,
+}
+
+impl UserStore {
+    pub fn new(users: &[UserCredentials]) -> Self {
+        let params = users
+            .iter()
+            .find_map(|user| {
+                let hash = PasswordHash::new(&user.password_hash).ok()?;
+                Params::try_from(&hash).ok()
+            })
+            .unwrap_or_default();
+
+        let dummy_password: String = OsRng.sample_iter(&Alphanumeric).take(32).map(char::from).collect();
+        let dummy_hash = hash_password_with(&dummy_password, params).expect("No se pudo generar el hash de relleno");
+
+        UserStore {
+            users: users
+                .iter()
+                .map(|user| (user.username.clone(), user.password_hash.clone()))
+                .collect(),
+            dummy_hash,
+        }
+    }
+
+    pub fn verify(&self, username: &str, password: &str) -> bool {
+        let (stored, exists) = match self.users.get(username) {
+            Some(hash) => (hash.as_str(), true),
+            None => (self.dummy_hash.as_str(), false),
+        };
+
+        let valid = match PasswordHash::new(stored) {
+            Ok(hash) => Argon2::default().verify_password(password.as_bytes(), &hash).is_ok(),
+            Err(e) => {
+                warn!("Hash de contraseña inválido para el usuario {:?}: {}", username, e);
+                false
+            }
+        };
+        valid && exists
+    }
+}
+
+// Genera un hash Argon2id en formato PHC para usar en la configuración
+pub fn hash_password(password: &str) -> Result<String, argon2::password_hash::Error> {
+    hash_password_with(password, Params::default())
+}
+
+pub fn hash_password_with(password: &str, params: Params) -> Result<String, argon2::password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    let argon2 = Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+    Ok(argon2.hash_password(password.as_bytes(), &salt)?.to_string())
+}
+
+#[instrument(name = "auth.login", skip_all, fields(outcome = Empty))]
+pub async fn login_handler(
+    guard: web::Data<LoginGuard>,
+    users: web::Data<UserStore>,
+    client_ip: ClientIp,
+    session: Session,
+    credentials: web::Json<LoginRequest>,
+) -> HttpResponse {
+    let started = Instant::now();
+    let LoginRequest { username, password } = credentials.into_inner();
+    let ip = client_ip.0;
+
+    let response = if let Some(remaining) = guard.locked_for(&username, ip) {
+        audit::record(&AuditEvent::LoginRejectedLocked { username: &username, ip });
+        Span::current().record("outcome", "locked");
+        AppError::TooManyRequests {
+            detail: "Demasiados intentos fallidos. Inténtalo más tarde.".to_string(),
+            retry_after: Some(remaining.as_secs()),
+        }
+        .error_response()
+    } else {
+        // Argon2 es costoso: se verifica fuera del hilo del worker
+        let store = users.clone();
+        let (name, pass) = (username.clone(), password);
+        let span = info_span!("auth.verify_password");
+        let valid = web::block(move || span.in_scope(|| store.verify(&name, &pass)))
+            .await
+            .unwrap_or(false);
+        Span::current().record("outcome", if valid { "success" } else { "failure" });
+
+        if valid {
+            guard.record_success(&username);
+            audit::record(&AuditEvent::LoginSucceeded { username: &username, ip });
+
+            let token: String = OsRng.sample_iter(&Alphanumeric).take(48).map(char::from).collect();
+            match session.insert("auth_token", &token) {
+                Ok(()) => {
+                    session.renew();
+                    HttpResponse::Ok().json(serde_json::json!({ "token": token }))
+                }
+                Err(e) => AppError::Internal(format!("Error al guardar la sesión: {}", e)).error_response(),
+            }
+        } else {
+            guard.record_failure(&username, ip);
+            audit::record(&AuditEvent::LoginFailed { username: &username, ip });
+            AppError::Unauthorized(INVALID_CREDENTIALS.to_string()).error_response()
+        }
+    };
+
+    // Igualar el tiempo de respuesta para no filtrar información por temporización
+    if let Some(wait) = guard.min_response().checked_sub(started.elapsed()) {
+        tokio::time::sleep(wait).await;
+    }
+    response
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BearerAuth {
+    // Sin cabecera Authorization
+    Absent,
+    // "Bearer <token>" con el token de la sesión
+    Valid,
+    // Otro esquema, token vacío o distinto del de la sesión, o sin sesión
+    Invalid,
+}
+
+// Comprueba "Authorization: Bearer <token>" (el esquema no distingue mayúsculas)
+// contra el token de la sesión
+pub fn bearer_auth(req: &HttpRequest) -> BearerAuth {
+    let Some(value) = req.headers().get(header::AUTHORIZATION) else {
+        return BearerAuth::Absent;
+    };
+    let bearer = value
+        .to_str()
+        .ok()
+        .and_then(|value| value.split_once(' '))
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+        .map(|(_, token)| token.trim())
+        .filter(|token| !token.is_empty());
+    let token = req.get_session().get::<String>("auth_token").unwrap_or(None);
+
+    match (token, bearer) {
+        (Some(token), Some(bearer)) if bool::from(token.as_bytes().ct_eq(bearer.as_bytes())) => BearerAuth::Valid,
+        _ => BearerAuth::Invalid,
+    }
+}
+
+// Extractor para los handlers que requieren sesión iniciada. Si la petición trae
+// cabecera Authorization, debe ser "Bearer" con el token de la sesión.
+#[derive(Debug)]
+pub struct Authenticated;
+
+impl FromRequest for Authenticated {
+    type Error = AppError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let logged_in = req.get_session().get::<String>("auth_token").unwrap_or(None).is_some();
+        ready(match bearer_auth(req) {
+            _ if !logged_in => Err(AppError::Unauthorized("No autorizado".to_string())),
+            BearerAuth::Absent | BearerAuth::Valid => Ok(Authenticated),
+            BearerAuth::Invalid => Err(AppError::Unauthorized("Token no válido".to_string())),
+        })
+    }
+}
@@ -1,46 +1,404 @@
 // metrics.rs
-use prometheus::{Counter, Encoder, Histogram, HistogramOpts, Opts, Registry, TextEncoder};
-use std::sync::Arc;
-use actix_web::{HttpResponse, Responder};
+use crate::config::MetricsConfig;
+use actix_web::body::{BodySize, BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::{header, Method, StatusCode};
+use actix_web::middleware::Next;
+use actix_web::web::Data;
+use actix_web::{Error, HttpRequest, HttpResponse};
+use prometheus::core::{Collector, Desc};
+use prometheus::proto::{MetricFamily, MetricType};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+use tokio::runtime::Handle;
+use tracing::error;
+
+// Etiqueta para las peticiones que no coinciden con ninguna ruta registrada
+const UNMATCHED_ROUTE: &str = "unmatched";
+// Etiqueta para las rutas que superan el máximo configurado
+const OTHER_ROUTE: &str = "other";
 
 pub struct Metrics {
     pub registry: Arc<Registry>,
-    pub http_requests_total: Counter,
-    pub request_duration: Histogram,
+    // Etiquetas: method, route, status
+    pub http_requests_total: IntCounterVec,
+    pub request_duration: HistogramVec,
+    // Etiquetas: method, route
+    pub request_size: HistogramVec,
+    pub response_size: HistogramVec,
+    pub in_flight: IntGauge,
+    // Pánicos capturados al procesar peticiones
+    pub panics: IntCounter,
+    routes: RouteLabels,
 }
 
 impl Metrics {
-    pub fn new(registry: Arc<Registry>) -> Self {
-        let http_requests_total = Counter::new("http_requests_total", "Total de solicitudes HTTP")
+    pub fn new(registry: Arc<Registry>, config: &MetricsConfig) -> Self {
+        let http_requests_total = IntCounterVec::new(
+            Opts::new("http_requests_total", "Total de solicitudes HTTP"),
+            &["method", "route", "status"],
+        )
             .expect("No se pudo crear el contador de solicitudes HTTP");
 
-        let request_duration = Histogram::with_opts(
-            HistogramOpts::from(Opts::new("http_request_duration_seconds", "Duración de las solicitudes HTTP en segundos"))
+        let request_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "Duración de las solicitudes HTTP en segundos")
+                .buckets(sorted_buckets(&config.duration_buckets)),
+            &["method", "route", "status"],
         )
             .expect("No se pudo crear el histograma de duración");
 
+        let request_size = HistogramVec::new(
+            HistogramOpts::new("http_request_size_bytes", "Tamaño del cuerpo de las solicitudes HTTP en bytes")
+                .buckets(sorted_buckets(&config.size_buckets)),
+            &["method", "route"],
+        )
+            .expect("No se pudo crear el histograma de tamaño de solicitud");
+
+        let response_size = HistogramVec::new(
+            HistogramOpts::new("http_response_size_bytes", "Tamaño del cuerpo de las respuestas HTTP en bytes (sin comprimir)")
+                .buckets(sorted_buckets(&config.size_buckets)),
+            &["method", "route"],
+        )
+            .expect("No se pudo crear el histograma de tamaño de respuesta");
+
+        let in_flight = IntGauge::new("http_requests_in_flight", "Solicitudes HTTP en curso")
+            .expect("No se pudo crear el indicador de solicitudes en curso");
+
+        let panics = IntCounter::new("http_panics_total", "Pánicos capturados al procesar solicitudes HTTP")
+            .expect("No se pudo crear el contador de pánicos");
+
         registry
             .register(Box::new(http_requests_total.clone()))
             .expect("No se pudo registrar http_requests_total");
         registry
             .register(Box::new(request_duration.clone()))
             .expect("No se pudo registrar request_duration");
+        registry
+            .register(Box::new(request_size.clone()))
+            .expect("No se pudo registrar request_size");
+        registry
+            .register(Box::new(response_size.clone()))
+            .expect("No se pudo registrar response_size");
+        registry
+            .register(Box::new(in_flight.clone()))
+            .expect("No se pudo registrar in_flight");
+        registry
+            .register(Box::new(panics.clone()))
+            .expect("No se pudo registrar http_panics_total");
 
         Metrics {
             registry,
             http_requests_total,
             request_duration,
+            request_size,
+            response_size,
+            in_flight,
+            panics,
+            routes: RouteLabels::new(config.max_routes),
+        }
+    }
+
+    fn record(&self, method: &str, route: &str, status: StatusCode, seconds: f64) {
+        let status = status_class(status);
+        self.http_requests_total.with_label_values(&[method, route, status]).inc();
+        self.request_duration.with_label_values(&[method, route, status]).observe(seconds);
+    }
+}
+
+// Prometheus exige límites estrictamente crecientes
+fn sorted_buckets(buckets: &[f64]) -> Vec<f64> {
+    let mut buckets: Vec<f64> = buckets.iter().copied().filter(|b| b.is_finite()).collect();
+    buckets.sort_by(f64::total_cmp);
+    buckets.dedup();
+    buckets
+}
+
+// Patrones de ruta vistos; limita el número de series aunque se registren
+// muchas rutas (las rutas sin patrón nunca usan la ruta real como etiqueta)
+struct RouteLabels {
+    max: usize,
+    seen: RwLock<HashSet<String>>,
+}
+
+impl RouteLabels {
+    fn new(max: usize) -> Self {
+        RouteLabels {
+            max,
+            seen: RwLock::new(HashSet::new()),
+        }
+    }
+
+    fn label(&self, pattern: Option<String>) -> String {
+        let Some(pattern) = pattern else {
+            return UNMATCHED_ROUTE.to_string();
+        };
+
+        if self.seen.read().unwrap_or_else(|poisoned| poisoned.into_inner()).contains(&pattern) {
+            return pattern;
+        }
+
+        let mut seen = self.seen.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if seen.len() < self.max || seen.contains(&pattern) {
+            seen.insert(pattern.clone());
+            pattern
+        } else {
+            OTHER_ROUTE.to_string()
+        }
+    }
+}
+
+// Los métodos no estándar se agrupan para no crear series arbitrarias
+fn method_label(method: &Method) -> &'static str {
+    match *method {
+        Method::GET => "GET",
+        Method::HEAD => "HEAD",
+        Method::POST => "POST",
+        Method::PUT => "PUT",
+        Method::PATCH => "PATCH",
+        Method::DELETE => "DELETE",
+        Method::OPTIONS => "OPTIONS",
+        Method::CONNECT => "CONNECT",
+        Method::TRACE => "TRACE",
+        _ => "OTHER",
+    }
+}
+
+fn status_class(status: StatusCode) -> &'static str {
+    match status.as_u16() {
+        100..=199 => "1xx",
+        200..=299 => "2xx",
+        300..=399 => "3xx",
+        400..=499 => "4xx",
+        _ => "5xx",
+    }
+}
+
+// Decrementa el indicador aunque la petición se cancele
+struct InFlightGuard<'a>(&'a IntGauge);
+
+impl<'a> InFlightGuard<'a> {
+    fn new(gauge: &'a IntGauge) -> Self {
+        gauge.inc();
+        InFlightGuard(gauge)
+    }
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+// Registra todas las peticiones con el patrón de ruta (no la ruta real), el método y la clase de estado
+pub async fn metrics_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let Some(metrics) = req.app_data::<Data<Metrics>>().cloned() else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+
+    let method = method_label(req.method());
+    let request_size = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+
+    let _in_flight = InFlightGuard::new(&metrics.in_flight);
+    let start = Instant::now();
+    let result = next.call(req).await;
+    let seconds = start.elapsed().as_secs_f64();
+
+    let res = match result {
+        Ok(res) => res.map_into_boxed_body(),
+        Err(e) => {
+            metrics.record(method, UNMATCHED_ROUTE, e.as_response_error().status_code(), seconds);
+            return Err(e);
         }
+    };
+
+    // El patrón se conoce después del enrutado
+    let route = metrics.routes.label(res.request().match_pattern());
+    metrics.record(method, &route, res.status(), seconds);
+
+    if let Some(size) = request_size {
+        metrics.request_size.with_label_values(&[method, &route]).observe(size as f64);
+    }
+    if let BodySize::Sized(size) = res.response().body().size() {
+        metrics.response_size.with_label_values(&[method, &route]).observe(size as f64);
+    }
+
+    Ok(res)
+}
+
+// Métricas del runtime principal de tokio, calculadas en cada lectura
+struct RuntimeCollector {
+    handle: Handle,
+    workers: IntGauge,
+    alive_tasks: IntGauge,
+    global_queue_depth: IntGauge,
+}
+
+impl RuntimeCollector {
+    fn new(handle: Handle) -> prometheus::Result<Self> {
+        Ok(RuntimeCollector {
+            handle,
+            workers: IntGauge::new("tokio_workers", "Hilos de trabajo del runtime de tokio")?,
+            alive_tasks: IntGauge::new("tokio_alive_tasks", "Tareas vivas en el runtime de tokio")?,
+            global_queue_depth: IntGauge::new("tokio_global_queue_depth", "Tareas pendientes en la cola global de tokio")?,
+        })
+    }
+}
+
+impl Collector for RuntimeCollector {
+    fn desc(&self) -> Vec<&Desc> {
+        [&self.workers, &self.alive_tasks, &self.global_queue_depth]
+            .into_iter()
+            .flat_map(|gauge| gauge.desc())
+            .collect()
     }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        let runtime = self.handle.metrics();
+        self.workers.set(runtime.num_workers() as i64);
+        self.alive_tasks.set(runtime.num_alive_tasks() as i64);
+        self.global_queue_depth.set(runtime.global_queue_depth() as i64);
+
+        [&self.workers, &self.alive_tasks, &self.global_queue_depth]
+            .into_iter()
+            .flat_map(|gauge| gauge.collect())
+            .collect()
+    }
+}
+
+// Registra las métricas del proceso (solo Linux) y las del runtime indicado
+pub fn register_process_collectors(registry: &Registry, runtime: Handle) -> prometheus::Result<()> {
+    #[cfg(target_os = "linux")]
+    registry.register(Box::new(prometheus::process_collector::ProcessCollector::for_self()))?;
+    registry.register(Box::new(RuntimeCollector::new(runtime)?))
 }
 
-pub async fn export_metrics(registry: Arc<Registry>) -> impl Responder {
+const OPENMETRICS_CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+// Formato de texto OpenMetrics 1.0
+pub fn encode_openmetrics(families: &[MetricFamily]) -> String {
+    let mut out = String::new();
+
+    for family in families {
+        let name = family.get_name();
+        let (base, kind) = match family.get_field_type() {
+            // En OpenMetrics el nombre de la familia de un contador no lleva "_total"
+            MetricType::COUNTER => (name.strip_suffix("_total").unwrap_or(name), "counter"),
+            MetricType::GAUGE => (name, "gauge"),
+            MetricType::HISTOGRAM => (name, "histogram"),
+            MetricType::SUMMARY => (name, "summary"),
+            MetricType::UNTYPED => (name, "unknown"),
+        };
+        let _ = writeln!(out, "# TYPE {} {}", base, kind);
+        let _ = writeln!(out, "# HELP {} {}", base, escape(family.get_help()));
+
+        for metric in family.get_metric() {
+            let labels: Vec<(&str, String)> = metric
+                .get_label()
+                .iter()
+                .map(|pair| (pair.get_name(), pair.get_value().to_string()))
+                .collect();
+
+            match family.get_field_type() {
+                MetricType::COUNTER => {
+                    write_sample(&mut out, &format!("{}_total", base), &labels, metric.get_counter().get_value())
+                }
+                MetricType::GAUGE => write_sample(&mut out, base, &labels, metric.get_gauge().get_value()),
+                MetricType::UNTYPED => write_sample(&mut out, base, &labels, metric.get_untyped().get_value()),
+                MetricType::HISTOGRAM => {
+                    let histogram = metric.get_histogram();
+                    let bucket_name = format!("{}_bucket", base);
+                    for bucket in histogram.get_bucket() {
+                        let mut bucket_labels = labels.clone();
+                        bucket_labels.push(("le", format_value(bucket.get_upper_bound())));
+                        write_sample(&mut out, &bucket_name, &bucket_labels, bucket.get_cumulative_count() as f64);
+                    }
+                    let mut inf_labels = labels.clone();
+                    inf_labels.push(("le", "+Inf".to_string()));
+                    write_sample(&mut out, &bucket_name, &inf_labels, histogram.get_sample_count() as f64);
+                    write_sample(&mut out, &format!("{}_count", base), &labels, histogram.get_sample_count() as f64);
+                    write_sample(&mut out, &format!("{}_sum", base), &labels, histogram.get_sample_sum());
+                }
+                MetricType::SUMMARY => {
+                    let summary = metric.get_summary();
+                    for quantile in summary.get_quantile() {
+                        let mut quantile_labels = labels.clone();
+                        quantile_labels.push(("quantile", format_value(quantile.get_quantile())));
+                        write_sample(&mut out, base, &quantile_labels, quantile.get_value());
+                    }
+                    write_sample(&mut out, &format!("{}_count", base), &labels, summary.get_sample_count() as f64);
+                    write_sample(&mut out, &format!("{}_sum", base), &labels, summary.get_sample_sum());
+                }
+            }
+        }
+    }
+
+    out.push_str("# EOF\n");
+    out
+}
+
+fn write_sample(out: &mut String, name: &str, labels: &[(&str, String)], value: f64) {
+    out.push_str(name);
+    if !labels.is_empty() {
+        let labels: Vec<String> = labels
+            .iter()
+            .map(|(name, value)| format!("{}=\"{}\"", name, escape(value)))
+            .collect();
+        let _ = write!(out, "{{{}}}", labels.join(","));
+    }
+    let _ = writeln!(out, " {}", format_value(value));
+}
+
+fn format_value(value: f64) -> String {
+    if value == f64::INFINITY {
+        "+Inf".to_string()
+    } else if value == f64::NEG_INFINITY {
+        "-Inf".to_string()
+    } else if value.is_nan() {
+        "NaN".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+// GET /metrics: formato OpenMetrics si el cliente lo acepta, si no el formato de texto de Prometheus
+pub async fn export_metrics(metrics: Data<Metrics>, req: HttpRequest) -> HttpResponse {
+    let metric_families = metrics.registry.gather();
+
+    let openmetrics = req
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|accept| accept.contains("application/openmetrics-text"));
+    if openmetrics {
+        return HttpResponse::Ok()
+            .content_type(OPENMETRICS_CONTENT_TYPE)
+            .body(encode_openmetrics(&metric_families));
+    }
+
     let encoder = TextEncoder::new();
-    let metric_families = registry.gather();
     let mut buffer = Vec::new();
-    encoder.encode(&metric_families, &mut buffer).unwrap();
+    if let Err(e) = encoder.encode(&metric_families, &mut buffer) {
+        error!("Error al codificar las métricas: {}", e);
+        return HttpResponse::InternalServerError().finish();
+    }
 
     HttpResponse::Ok()
-        .content_type("text/plain; charset=utf-8")
+        .content_type(encoder.format_type())
         .body(buffer)
 }
@@ -0,0 +1,287 @@
+// migrations.rs
+use crate::config::Config;
+use crate::db::{with_pool, Database};
+use sqlx::migrate::{Migrate, MigrateError, Migrator};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use time::macros::format_description;
+use time::OffsetDateTime;
+use tracing::info;
+
+// Migraciones incluidas en el binario, una carpeta por motor
+static SQLITE_MIGRATIONS: Migrator = sqlx::migrate!("./migrations/sqlite");
+static POSTGRES_MIGRATIONS: Migrator = sqlx::migrate!("./migrations/postgres");
+
+const USAGE: &str = "Uso: migrate up | down [versión] | status | new <descripción>";
+
+// Las incluidas o las leídas de migrations_dir
+enum Migrations {
+    Embedded(&'static Migrator),
+    Directory(Migrator),
+}
+
+impl Deref for Migrations {
+    type Target = Migrator;
+
+    fn deref(&self) -> &Migrator {
+        match self {
+            Migrations::Embedded(migrator) => migrator,
+            Migrations::Directory(migrator) => migrator,
+        }
+    }
+}
+
+async fn migrations(database: &Database) -> io::Result<Migrations> {
+    match database.migrations_dir() {
+        Some(dir) => Migrator::new(dir).await.map(Migrations::Directory).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("No se pudieron leer las migraciones de '{}': {}", dir.display(), e),
+            )
+        }),
+        None => Ok(Migrations::Embedded(match database.backend() {
+            "postgres" => &POSTGRES_MIGRATIONS,
+            _ => &SQLITE_MIGRATIONS,
+        })),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    // Aplicada, pero el SQL ya no coincide (checksum distinto)
+    Modified,
+    // Aplicada, pero ya no existe en las migraciones
+    Missing,
+    // Falló a medias; hay que revisarla a mano
+    Dirty,
+}
+
+impl MigrationState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            MigrationState::Applied => "aplicada",
+            MigrationState::Pending => "pendiente",
+            MigrationState::Modified => "modificada",
+            MigrationState::Missing => "desconocida",
+            MigrationState::Dirty => "incompleta",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub state: MigrationState,
+}
+
+// Estado de cada migración, por versión
+pub async fn status(database: &Database) -> io::Result<Vec<MigrationStatus>> {
+    let migrator = migrations(database).await?;
+    let (applied, dirty) = with_pool!(database.pool(), |pool| {
+        let mut conn = pool.acquire().await.map_err(migrate_error)?;
+        conn.ensure_migrations_table().await.map_err(migrate_error)?;
+        let dirty = conn.dirty_version().await.map_err(migrate_error)?;
+        let applied = conn.list_applied_migrations().await.map_err(migrate_error)?;
+        (applied, dirty)
+    });
+    let mut applied: HashMap<i64, _> = applied.into_iter().map(|migration| (migration.version, migration.checksum)).collect();
+
+    let mut migrations: Vec<MigrationStatus> = migrator
+        .iter()
+        .filter(|migration| migration.migration_type.is_up_migration())
+        .map(|migration| {
+            let state = match applied.remove(&migration.version) {
+                _ if dirty == Some(migration.version) => MigrationState::Dirty,
+                Some(checksum) if checksum == migration.checksum => MigrationState::Applied,
+                Some(_) => MigrationState::Modified,
+                None => MigrationState::Pending,
+            };
+            MigrationStatus {
+                version: migration.version,
+                description: migration.description.to_string(),
+                state,
+            }
+        })
+        .collect();
+    migrations.extend(applied.into_keys().map(|version| MigrationStatus {
+        version,
+        description: String::new(),
+        state: if dirty == Some(version) { MigrationState::Dirty } else { MigrationState::Missing },
+    }));
+    migrations.sort_by_key(|migration| migration.version);
+    Ok(migrations)
+}
+
+// Aplica las migraciones pendientes tras comprobar el checksum de las aplicadas.
+// Devuelve las versiones aplicadas.
+pub async fn up(database: &Database) -> io::Result<Vec<i64>> {
+    let pending: Vec<i64> = status(database)
+        .await?
+        .into_iter()
+        .filter(|migration| migration.state == MigrationState::Pending)
+        .map(|migration| migration.version)
+        .collect();
+    let migrator = migrations(database).await?;
+    with_pool!(database.pool(), |pool| migrator.run(pool).await).map_err(migrate_error)?;
+    for version in &pending {
+        info!("Migración {} aplicada", version);
+    }
+    Ok(pending)
+}
+
+// Revierte las migraciones posteriores a `target`; sin versión, solo la última.
+// Devuelve las versiones revertidas.
+pub async fn down(database: &Database, target: Option<i64>) -> io::Result<Vec<i64>> {
+    let applied: Vec<i64> = status(database)
+        .await?
+        .into_iter()
+        .filter(|migration| migration.state != MigrationState::Pending)
+        .map(|migration| migration.version)
+        .collect();
+    let target = match target {
+        Some(target) => target,
+        None => match applied.as_slice() {
+            [] => return Ok(Vec::new()),
+            [.., previous, _] => *previous,
+            [_] => 0,
+        },
+    };
+
+    let migrator = migrations(database).await?;
+    let reverted: Vec<i64> = applied.into_iter().filter(|version| *version > target).rev().collect();
+    // sqlx se salta en silencio las que no tienen .down.sql
+    if let Some(version) = reverted.iter().find(|version| {
+        !migrator
+            .iter()
+            .any(|migration| migration.version == **version && migration.migration_type.is_down_migration())
+    }) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("La migración {} no se puede revertir (no tiene .down.sql)", version),
+        ));
+    }
+
+    with_pool!(database.pool(), |pool| migrator.undo(pool, target).await).map_err(migrate_error)?;
+    for version in &reverted {
+        info!("Migración {} revertida", version);
+    }
+    Ok(reverted)
+}
+
+// Error si hay migraciones pendientes o alguna aplicada no coincide con las actuales
+pub async fn verify(database: &Database) -> io::Result<()> {
+    let problems: Vec<String> = status(database)
+        .await?
+        .into_iter()
+        .filter(|migration| migration.state != MigrationState::Applied)
+        .map(|migration| format!("{} ({})", migration.version, migration.state.label()))
+        .collect();
+    if problems.is_empty() {
+        return Ok(());
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("La base de datos no está al día; ejecuta `migrate up`: {}", problems.join(", ")),
+    ))
+}
+
+// Crea la pareja .up.sql/.down.sql para cada motor. La versión es la fecha y hora UTC.
+pub fn new_migration(dir: &Path, description: &str) -> io::Result<Vec<PathBuf>> {
+    let slug = description
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect::<Vec<_>>()
+        .join("_");
+    if slug.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "La migración necesita una descripción"));
+    }
+    let version = OffsetDateTime::now_utc()
+        .format(format_description!("[year][month][day][hour][minute][second]"))
+        .map_err(io::Error::other)?;
+
+    let mut created = Vec::new();
+    for backend in ["sqlite", "postgres"] {
+        let backend_dir = dir.join(backend);
+        fs::create_dir_all(&backend_dir)?;
+        for (suffix, comment) in [("up", "Cambios"), ("down", "Deshace los cambios de")] {
+            let path = backend_dir.join(format!("{}_{}.{}.sql", version, slug, suffix));
+            fs::write(&path, format!("-- {} {} ({})\n", comment, description.trim(), backend))?;
+            created.push(path);
+        }
+    }
+    Ok(created)
+}
+
+// Subcomando `migrate` del binario
+pub async fn run_command(args: &[String], config: &Config) -> io::Result<()> {
+    let usage = || io::Error::new(io::ErrorKind::InvalidInput, USAGE);
+    let command = args.first().map(String::as_str).ok_or_else(usage)?;
+
+    if command == "new" {
+        let description = args[1..].join(" ");
+        let dir = config.database.migrations_dir.as_deref().unwrap_or("./migrations");
+        for path in new_migration(Path::new(dir), &description)? {
+            println!("Creada {}", path.display());
+        }
+        return Ok(());
+    }
+
+    let database = Database::connect(&config.database)?;
+    let result = match (command, &args[1..]) {
+        ("up", []) => up(&database).await.map(|applied| {
+            if applied.is_empty() {
+                println!("No hay migraciones pendientes");
+            }
+            for version in applied {
+                println!("Aplicada {}", version);
+            }
+        }),
+        ("down", [] | [_]) => {
+            let target = match args.get(1) {
+                Some(version) => Some(version.parse().map_err(|_| usage())?),
+                None => None,
+            };
+            down(&database, target).await.map(|reverted| {
+                if reverted.is_empty() {
+                    println!("No hay migraciones que revertir");
+                }
+                for version in reverted {
+                    println!("Revertida {}", version);
+                }
+            })
+        }
+        ("status", []) => status(&database).await.map(|migrations| {
+            for migration in migrations {
+                println!("{:<16} {:<12} {}", migration.version, migration.state.label(), migration.description);
+            }
+        }),
+        _ => Err(usage()),
+    };
+    database.close().await;
+    result
+}
+
+fn migrate_error(e: impl Into<MigrateError>) -> io::Error {
+    match e.into() {
+        MigrateError::VersionMismatch(version) => io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("La migración {} ya aplicada ha cambiado (checksum distinto)", version),
+        ),
+        MigrateError::VersionMissing(version) => io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("La migración {} está aplicada pero no existe", version),
+        ),
+        MigrateError::Dirty(version) => io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("La migración {} falló a medias; revísala a mano", version),
+        ),
+        other => io::Error::other(format!("Error de migración: {}", other)),
+    }
+}
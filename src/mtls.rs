@@ -0,0 +1,232 @@
+// mtls.rs
+use crate::config::{ClientAuth, MtlsConfig, MtlsIdentityRule};
+use crate::error::AppError;
+use crate::paths;
+use actix_tls::accept::rustls_0_23::TlsStream;
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{Extensions, Payload, ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::rt::net::TcpStream;
+use actix_web::web::Data;
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest, ResponseError};
+use rustls::crypto::ring;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::CertificateDer;
+use rustls::server::danger::ClientCertVerifier;
+use rustls::server::WebPkiClientVerifier;
+use rustls::RootCertStore;
+use std::any::Any;
+use std::future::{ready, Ready};
+use std::io;
+use std::sync::Arc;
+use tracing::field::Empty;
+use tracing::{info_span, warn};
+use x509_parser::prelude::{FromDer, GeneralName, X509Certificate};
+
+// Datos del certificado de cliente presentado en el handshake TLS
+#[derive(Debug, Clone, Default)]
+pub struct PeerCertificate {
+    pub subject: String,
+    pub common_name: Option<String>,
+    // SAN de tipo DNS, URI y email
+    pub alt_names: Vec<String>,
+}
+
+impl PeerCertificate {
+    pub fn from_der(der: &[u8]) -> Option<PeerCertificate> {
+        let (_, cert) = X509Certificate::from_der(der).ok()?;
+
+        let common_name = cert
+            .subject()
+            .iter_common_name()
+            .next()
+            .and_then(|cn| cn.as_str().ok())
+            .map(str::to_string);
+
+        let alt_names = match cert.subject_alternative_name() {
+            Ok(Some(san)) => san
+                .value
+                .general_names
+                .iter()
+                .filter_map(|name| match name {
+                    GeneralName::DNSName(value) | GeneralName::URI(value) | GeneralName::RFC822Name(value) => {
+                        Some(value.to_string())
+                    }
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        Some(PeerCertificate {
+            subject: cert.subject().to_string(),
+            common_name,
+            alt_names,
+        })
+    }
+}
+
+// Callback on_connect: guarda el certificado de cliente en los datos de la conexión
+pub fn on_connect(connection: &dyn Any, data: &mut Extensions) {
+    let Some(tls) = connection.downcast_ref::<TlsStream<TcpStream>>() else {
+        return;
+    };
+
+    let (_, session) = tls.get_ref();
+    let peer = session
+        .peer_certificates()
+        .and_then(|certs| certs.first())
+        .and_then(|cert| PeerCertificate::from_der(cert.as_ref()));
+    if let Some(peer) = peer {
+        data.insert(peer);
+    }
+}
+
+// Verificador de certificados de cliente según el modo del listener
+pub fn client_verifier(mode: ClientAuth, config: &MtlsConfig) -> io::Result<Option<Arc<dyn ClientCertVerifier>>> {
+    if mode == ClientAuth::None {
+        return Ok(None);
+    }
+
+    let invalid = |e: &dyn std::fmt::Display| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("CA de clientes '{}': {}", config.ca_bundle, e))
+    };
+
+    let mut roots = RootCertStore::empty();
+    for cert in CertificateDer::pem_file_iter(&config.ca_bundle).map_err(|e| invalid(&e))? {
+        roots.add(cert.map_err(|e| invalid(&e))?).map_err(|e| invalid(&e))?;
+    }
+    if roots.is_empty() {
+        return Err(invalid(&"no contiene certificados"));
+    }
+
+    let builder = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), Arc::new(ring::default_provider()));
+    let builder = match mode {
+        ClientAuth::Optional => builder.allow_unauthenticated(),
+        _ => builder,
+    };
+    Ok(Some(builder.build().map_err(|e| invalid(&e))?))
+}
+
+// Reglas que asignan roles a las identidades y roles exigidos por prefijo de ruta
+pub struct MtlsAuthorizer {
+    identities: Vec<MtlsIdentityRule>,
+    routes: Vec<(String, Vec<String>)>,
+}
+
+impl MtlsAuthorizer {
+    pub fn new(config: &MtlsConfig) -> Self {
+        let mut routes: Vec<_> = config
+            .routes
+            .iter()
+            .map(|route| (route.path_prefix.clone(), route.roles.clone()))
+            .collect();
+        // El prefijo más largo tiene prioridad
+        routes.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+
+        MtlsAuthorizer {
+            identities: config.identities.clone(),
+            routes,
+        }
+    }
+
+    pub fn roles_for(&self, peer: &PeerCertificate) -> Vec<String> {
+        let mut roles: Vec<String> = self
+            .identities
+            .iter()
+            .filter(|rule| {
+                let cn_matches = rule.common_name.as_ref().is_none_or(|cn| peer.common_name.as_ref() == Some(cn));
+                let san_matches = rule.san.as_ref().is_none_or(|san| peer.alt_names.contains(san));
+                // Una regla sin criterios no debe aceptar cualquier certificado
+                (rule.common_name.is_some() || rule.san.is_some()) && cn_matches && san_matches
+            })
+            .flat_map(|rule| rule.roles.iter().cloned())
+            .collect();
+        roles.sort();
+        roles.dedup();
+        roles
+    }
+
+    fn required_roles(&self, path: &str) -> Option<&[String]> {
+        self.routes
+            .iter()
+            .find(|(prefix, _)| paths::matches_prefix(path, prefix))
+            .map(|(_, roles)| roles.as_slice())
+    }
+}
+
+// Extractor con la identidad del certificado de cliente y sus roles
+#[derive(Debug, Clone)]
+pub struct ClientIdentity {
+    pub certificate: PeerCertificate,
+    pub roles: Vec<String>,
+}
+
+impl ClientIdentity {
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+
+    // El certificado se guarda en los datos de la conexión; también se acepta en las
+    // extensiones de la petición, donde solo puede ponerlo el propio servidor
+    pub(crate) fn from_http_request(req: &HttpRequest) -> Option<ClientIdentity> {
+        let certificate = req
+            .conn_data::<PeerCertificate>()
+            .cloned()
+            .or_else(|| req.extensions().get::<PeerCertificate>().cloned())?;
+        let roles = req
+            .app_data::<Data<MtlsAuthorizer>>()
+            .map(|authorizer| authorizer.roles_for(&certificate))
+            .unwrap_or_default();
+        Some(ClientIdentity { certificate, roles })
+    }
+}
+
+impl FromRequest for ClientIdentity {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(
+            ClientIdentity::from_http_request(req)
+                .ok_or_else(|| actix_web::error::ErrorUnauthorized("Certificado de cliente requerido")),
+        )
+    }
+}
+
+// Exige un certificado de cliente con alguno de los roles configurados para la ruta
+pub async fn mtls_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let required = req
+        .app_data::<Data<MtlsAuthorizer>>()
+        .and_then(|authorizer| authorizer.required_roles(paths::route_path(&req)).map(<[String]>::to_vec));
+    let Some(required) = required else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+
+    let span = info_span!("mtls.authorize", client.subject = Empty, allowed = Empty);
+    let rejection = span.in_scope(|| match ClientIdentity::from_http_request(req.request()) {
+        Some(identity) => {
+            span.record("client.subject", identity.certificate.subject.as_str());
+            if required.iter().any(|role| identity.has_role(role)) {
+                return None;
+            }
+            warn!(
+                "mTLS: '{}' sin rol para {} (requiere {:?})",
+                identity.certificate.subject,
+                req.path(),
+                required
+            );
+            Some("Certificado de cliente no autorizado")
+        }
+        None => Some("Certificado de cliente requerido"),
+    });
+    span.record("allowed", rejection.is_none());
+
+    match rejection {
+        None => Ok(next.call(req).await?.map_into_boxed_body()),
+        Some(reason) => Ok(req.into_response(AppError::Forbidden(reason.to_string()).error_response())),
+    }
+}
@@ -0,0 +1,18 @@
+// paths.rs
+use actix_web::dev::ServiceRequest;
+
+// Ruta tal como la ve el router: sin la codificación "%XX" (salvo %2F, %25 y %2B).
+// Las reglas por ruta se comparan con ella para que "/l%6fgin" no esquive una regla
+// sobre "/login".
+pub fn route_path(req: &ServiceRequest) -> &str {
+    req.match_info().as_str()
+}
+
+// Prefijo por segmentos completos: "/embed" incluye "/embed" y "/embed/x", pero no
+// "/embedded". Un prefijo que acaba en "/" incluye todo lo que cuelga de él.
+pub fn matches_prefix(path: &str, prefix: &str) -> bool {
+    match path.strip_prefix(prefix) {
+        Some(rest) => prefix.ends_with('/') || rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
@@ -0,0 +1,77 @@
+// rate_limit.rs
+use crate::client_ip::ClientIp;
+use crate::config::RateLimitConfig;
+use crate::dos::{self, DosGuard};
+use crate::error::AppError;
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::web::Data;
+use actix_web::{Error, ResponseError};
+use governor::clock::{Clock, DefaultClock};
+use governor::{DefaultDirectRateLimiter, DefaultKeyedRateLimiter, Quota, RateLimiter};
+use std::net::IpAddr;
+use std::num::NonZeroU32;
+use tracing::field::Empty;
+use tracing::{info_span, warn};
+
+pub struct RateLimiters {
+    pub global: DefaultDirectRateLimiter,
+    pub per_client: DefaultKeyedRateLimiter<IpAddr>,
+}
+
+impl RateLimiters {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        let global = NonZeroU32::new(config.global_per_minute).unwrap_or(NonZeroU32::MAX);
+        let per_client = NonZeroU32::new(config.per_client_per_minute).unwrap_or(NonZeroU32::MAX);
+
+        RateLimiters {
+            global: RateLimiter::direct(Quota::per_minute(global)),
+            per_client: RateLimiter::keyed(Quota::per_minute(per_client)),
+        }
+    }
+}
+
+pub async fn rate_limit_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let Some(limiters) = req.app_data::<Data<RateLimiters>>().cloned() else {
+        // Sin limitadores configurados no se limita nada
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+
+    let span = info_span!("rate_limit", client.address = Empty, limited = Empty);
+    // Si se supera un límite, segundos hasta que se vuelva a admitir la petición
+    let retry_after = span.in_scope(|| {
+        let now = DefaultClock::default().now();
+
+        //Verificar el limitador global
+        if let Err(not_until) = limiters.global.check() {
+            warn!("Límite global de peticiones superado");
+            return Some(not_until.wait_time_from(now).as_secs());
+        }
+
+        // Verificar el limitador por cliente con la IP resuelta tras los proxies de confianza
+        let client_ip = ClientIp::from_service_request(&req);
+        span.record("client.address", client_ip.to_string());
+        if let Err(not_until) = limiters.per_client.check_key(&client_ip.0) {
+            warn!("Límite de peticiones superado para el cliente {}", client_ip);
+            if let Some(guard) = req.app_data::<Data<DosGuard>>() {
+                guard.strike(client_ip.0, dos::RATE_LIMIT);
+            }
+            return Some(not_until.wait_time_from(now).as_secs());
+        }
+        None
+    });
+    span.record("limited", retry_after.is_some());
+    if let Some(retry_after) = retry_after {
+        let error = AppError::TooManyRequests {
+            detail: "Demasiadas peticiones. Inténtalo más tarde.".to_string(),
+            retry_after: Some(retry_after),
+        };
+        return Ok(req.into_response(error.error_response()).map_into_right_body());
+    }
+
+    Ok(next.call(req).await?.map_into_left_body())
+}
@@ -0,0 +1,47 @@
+// recovery.rs
+use crate::error::{AppError, ErrorContext};
+use crate::metrics::Metrics;
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::web::Data;
+use actix_web::Error;
+use futures_util::FutureExt;
+use std::any::Any;
+use std::panic::AssertUnwindSafe;
+use tracing::error;
+
+// Convierte un pánico durante la petición en un 500 con el formato de error común,
+// en lugar de cortar la conexión. El log lleva el ID de petición del span actual.
+pub async fn panic_recovery_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    // La petición pasa al siguiente servicio y se pierde con el pánico:
+    // se guarda antes lo necesario para responder
+    let context = ErrorContext::new(req.request());
+    let request_line = format!("{} {}", req.method(), req.path());
+    let metrics = req.app_data::<Data<Metrics>>().cloned();
+
+    match AssertUnwindSafe(next.call(req)).catch_unwind().await {
+        Ok(result) => Ok(result?.map_into_boxed_body()),
+        Err(payload) => {
+            let message = panic_message(payload.as_ref());
+            error!("Pánico al procesar {}: {}", request_line, message);
+            if let Some(metrics) = metrics {
+                metrics.panics.inc();
+            }
+            Err(context.into_error(AppError::Internal(message)).into())
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "pánico sin mensaje".to_string()
+    }
+}
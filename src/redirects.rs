@@ -0,0 +1,284 @@
+// redirects.rs
+use crate::access::AccessControl;
+use crate::client_ip::RequestOrigin;
+use crate::config::{RedirectMap, RedirectRule, RedirectRulesFile, RedirectsConfig};
+use crate::file_watch;
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header;
+use actix_web::http::{StatusCode, Uri};
+use actix_web::middleware::Next;
+use actix_web::web::Data;
+use actix_web::{Error, HttpResponse};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{debug, error, info};
+
+// Resultado de aplicar las reglas a una petición
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Redirect { location: String, status: StatusCode },
+    // Ruta (con query) que se sirve en lugar de la pedida
+    Rewrite(String),
+}
+
+// Reglas de redirección con recarga en caliente. Si la recarga falla se mantienen
+// las reglas anteriores.
+pub struct Redirects {
+    config: RwLock<RedirectsConfig>,
+    rules: RwLock<Arc<RuleSet>>,
+}
+
+impl Redirects {
+    pub fn new(config: &RedirectsConfig) -> io::Result<Self> {
+        Ok(Redirects {
+            rules: RwLock::new(Arc::new(RuleSet::load(config)?)),
+            config: RwLock::new(config.clone()),
+        })
+    }
+
+    // Vuelve a leer el archivo de reglas y los mapas CSV
+    pub fn reload(&self) -> io::Result<()> {
+        let config = self.config.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+        self.replace(&config)
+    }
+
+    // Aplica una configuración nueva (por ejemplo, tras recargar config.toml)
+    pub fn replace(&self, config: &RedirectsConfig) -> io::Result<()> {
+        let rules = RuleSet::load(config)?;
+        info!("Reglas de redirección recargadas: {} reglas, {} rutas en mapas", rules.rules.len(), rules.map.len());
+        *self.rules.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::new(rules);
+        *self.config.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = config.clone();
+        Ok(())
+    }
+
+    // Archivo de reglas y CSV a vigilar
+    pub fn watched_files(&self) -> Vec<PathBuf> {
+        let config = self.config.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        config
+            .rules_file
+            .iter()
+            .map(PathBuf::from)
+            .chain(config.maps.iter().map(|map| PathBuf::from(&map.file)))
+            .collect()
+    }
+
+    pub fn resolve(&self, host: &str, path: &str, query: &str) -> Option<Action> {
+        let rules = self.rules.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+        rules.resolve(host, path, query)
+    }
+}
+
+#[derive(Default)]
+struct RuleSet {
+    // Rutas exactas de los mapas CSV
+    map: HashMap<String, MapEntry>,
+    rules: Vec<CompiledRule>,
+}
+
+struct MapEntry {
+    target: String,
+    status: StatusCode,
+    preserve_query: bool,
+}
+
+struct CompiledRule {
+    host: Option<String>,
+    pattern: Regex,
+    target: String,
+    // None = reescritura interna
+    status: Option<StatusCode>,
+    preserve_query: bool,
+}
+
+impl RuleSet {
+    fn load(config: &RedirectsConfig) -> io::Result<Self> {
+        let mut rules = config.rules.clone();
+        let mut maps = config.maps.clone();
+        if let Some(path) = &config.rules_file {
+            let content = fs::read_to_string(path)
+                .map_err(|e| io::Error::new(e.kind(), format!("No se pudo leer '{}': {}", path, e)))?;
+            let file: RedirectRulesFile = toml::from_str(&content)
+                .map_err(|e| invalid(format!("archivo de reglas '{}' inválido: {}", path, e)))?;
+            rules.extend(file.rules);
+            maps.extend(file.maps);
+        }
+
+        let mut set = RuleSet::default();
+        for rule in &rules {
+            set.rules.push(CompiledRule::new(rule)?);
+        }
+        for map in &maps {
+            set.load_map(map)?;
+        }
+        Ok(set)
+    }
+
+    fn load_map(&mut self, map: &RedirectMap) -> io::Result<()> {
+        let default_status = redirect_status(map.status)?;
+        let content = fs::read_to_string(&map.file)
+            .map_err(|e| io::Error::new(e.kind(), format!("No se pudo leer '{}': {}", map.file, e)))?;
+
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let status = match fields.as_slice() {
+                [_, _] => default_status,
+                [_, _, status] => status
+                    .parse()
+                    .map_err(|_| invalid(format!("estado '{}' no numérico", status)))
+                    .and_then(redirect_status)
+                    .map_err(|e| invalid(format!("{}:{}: {}", map.file, number + 1, e)))?,
+                _ => return Err(invalid(format!("{}:{}: se esperaba 'origen,destino[,estado]'", map.file, number + 1))),
+            };
+            self.map.insert(
+                fields[0].to_string(),
+                MapEntry {
+                    target: fields[1].to_string(),
+                    status,
+                    preserve_query: map.preserve_query,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    fn resolve(&self, host: &str, path: &str, query: &str) -> Option<Action> {
+        if let Some(entry) = self.map.get(path) {
+            return Some(Action::Redirect {
+                location: with_query(entry.target.clone(), query, entry.preserve_query),
+                status: entry.status,
+            });
+        }
+
+        let host = host.to_ascii_lowercase();
+        self.rules.iter().find_map(|rule| {
+            if !rule.matches_host(&host) {
+                return None;
+            }
+            let captures = rule.pattern.captures(path)?;
+            let mut target = String::new();
+            captures.expand(&rule.target, &mut target);
+            let target = with_query(target, query, rule.preserve_query);
+            Some(match rule.status {
+                Some(status) => Action::Redirect { location: target, status },
+                None => Action::Rewrite(target),
+            })
+        })
+    }
+}
+
+impl CompiledRule {
+    fn new(rule: &RedirectRule) -> io::Result<Self> {
+        let pattern = Regex::new(&format!("^(?:{})$", rule.pattern))
+            .map_err(|e| invalid(format!("patrón '{}' inválido: {}", rule.pattern, e)))?;
+        let status = if rule.rewrite {
+            if !rule.target.starts_with('/') {
+                return Err(invalid(format!("la reescritura de '{}' debe apuntar a una ruta local", rule.pattern)));
+            }
+            None
+        } else {
+            Some(redirect_status(rule.status)?)
+        };
+
+        Ok(CompiledRule {
+            host: rule.host.as_ref().map(|host| host.to_ascii_lowercase()),
+            pattern,
+            target: rule.target.clone(),
+            status,
+            preserve_query: rule.preserve_query,
+        })
+    }
+
+    fn matches_host(&self, host: &str) -> bool {
+        match self.host.as_deref() {
+            None => true,
+            Some(expected) => match expected.strip_prefix("*.") {
+                Some(domain) => host
+                    .strip_suffix(domain)
+                    .is_some_and(|subdomain| subdomain.len() > 1 && subdomain.ends_with('.')),
+                None => host == expected,
+            },
+        }
+    }
+}
+
+fn redirect_status(status: u16) -> io::Result<StatusCode> {
+    match status {
+        301 | 302 | 307 | 308 => Ok(StatusCode::from_u16(status).unwrap_or(StatusCode::MOVED_PERMANENTLY)),
+        _ => Err(invalid(format!("estado de redirección {} no admitido (301, 302, 307 o 308)", status))),
+    }
+}
+
+fn with_query(target: String, query: &str, preserve_query: bool) -> String {
+    if !preserve_query || query.is_empty() {
+        target
+    } else if target.contains('?') {
+        format!("{}&{}", target, query)
+    } else {
+        format!("{}?{}", target, query)
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("Redirecciones: {}", message))
+}
+
+pub async fn redirects_middleware(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let Some(redirects) = req.app_data::<Data<Redirects>>().cloned() else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+
+    let host = RequestOrigin::from_request_parts(req.request()).host;
+    // Sin el puerto (también en IPv6: "[::1]:8080")
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name.to_string(),
+        _ => host,
+    };
+
+    match redirects.resolve(&host, req.path(), req.query_string()) {
+        Some(Action::Redirect { location, status }) => {
+            debug!("Redirección {} {} -> {}", status.as_u16(), req.path(), location);
+            let res = HttpResponse::build(status).insert_header((header::LOCATION, location)).finish();
+            Ok(req.into_response(res).map_into_right_body())
+        }
+        Some(Action::Rewrite(target)) => {
+            debug!("Reescritura {} -> {}", req.path(), target);
+            match target.parse::<Uri>() {
+                Ok(uri) => {
+                    // Se actualiza también la ruta que usa el router
+                    req.match_info_mut().get_mut().update(&uri);
+                    req.head_mut().uri = uri;
+                    // Las listas de acceso también se aplican a la ruta reescrita
+                    if let Some(res) = req.app_data::<Data<AccessControl>>().and_then(|access| access.check(&req)) {
+                        return Ok(req.into_response(res).map_into_right_body());
+                    }
+                }
+                Err(e) => error!("Destino de reescritura inválido '{}': {}", target, e),
+            }
+            Ok(next.call(req).await?.map_into_left_body())
+        }
+        None => Ok(next.call(req).await?.map_into_left_body()),
+    }
+}
+
+// Monitorear el archivo de reglas y los CSV y recargarlos al cambiar
+pub async fn monitor_rules(redirects: Arc<Redirects>) -> io::Result<()> {
+    file_watch::watch_files("reglas de redirección", &redirects.watched_files(), Duration::from_millis(200), || {
+        if let Err(e) = redirects.reload() {
+            error!("Error al recargar las redirecciones (se mantienen las anteriores): {}", e);
+        }
+    })
+    .await
+}
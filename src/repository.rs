@@ -0,0 +1,124 @@
+// repository.rs
+use crate::db::{with_pool, Database};
+use crate::items::{Item, ItemPatch, ListParams, NewItem, SortField};
+use futures_util::future::BoxFuture;
+
+// Acceso a los ítems, independiente del motor de base de datos
+pub trait ItemRepository: Send + Sync {
+    // Página de ítems y total de los que cumplen el filtro
+    fn list<'a>(&'a self, params: &'a ListParams) -> BoxFuture<'a, Result<(Vec<Item>, u64), sqlx::Error>>;
+    fn get(&self, id: i64) -> BoxFuture<'_, Result<Option<Item>, sqlx::Error>>;
+    fn create(&self, new: NewItem) -> BoxFuture<'_, Result<Item, sqlx::Error>>;
+    // None si el ítem no existe
+    fn replace(&self, id: i64, new: NewItem) -> BoxFuture<'_, Result<Option<Item>, sqlx::Error>>;
+    fn update(&self, id: i64, patch: ItemPatch) -> BoxFuture<'_, Result<Option<Item>, sqlx::Error>>;
+    fn delete(&self, id: i64) -> BoxFuture<'_, Result<bool, sqlx::Error>>;
+}
+
+const ITEM_COLUMNS: &str = "id, name, description";
+
+// Implementación SQL común a SQLite y PostgreSQL
+pub struct SqlItemRepository {
+    database: Database,
+}
+
+impl SqlItemRepository {
+    pub fn new(database: Database) -> Self {
+        SqlItemRepository { database }
+    }
+}
+
+impl ItemRepository for SqlItemRepository {
+    fn list<'a>(&'a self, params: &'a ListParams) -> BoxFuture<'a, Result<(Vec<Item>, u64), sqlx::Error>> {
+        Box::pin(async move {
+            // Búsqueda literal: se escapan los comodines de LIKE
+            let pattern = params.filter.as_ref().map(|q| {
+                format!("%{}%", q.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"))
+            });
+            let filter = if pattern.is_some() {
+                "WHERE LOWER(name) LIKE $1 ESCAPE '\\' OR LOWER(description) LIKE $1 ESCAPE '\\'"
+            } else {
+                ""
+            };
+            let direction = if params.descending { "DESC" } else { "ASC" };
+            let order = match params.sort {
+                SortField::Id => format!("id {}", direction),
+                SortField::Name => format!("LOWER(name) {0}, id {0}", direction),
+            };
+            let (limit, offset) = if pattern.is_some() { ("$2", "$3") } else { ("$1", "$2") };
+            let select = format!(
+                "SELECT {} FROM items {} ORDER BY {} LIMIT {} OFFSET {}",
+                ITEM_COLUMNS, filter, order, limit, offset
+            );
+            let count = format!("SELECT COUNT(*) FROM items {}", filter);
+
+            with_pool!(self.database.pool(), |pool| {
+                let mut query = sqlx::query_as::<_, Item>(&select);
+                let mut total = sqlx::query_scalar::<_, i64>(&count);
+                if let Some(pattern) = &pattern {
+                    query = query.bind(pattern);
+                    total = total.bind(pattern);
+                }
+                let items = query.bind(i64::from(params.limit)).bind(params.offset as i64).fetch_all(pool).await?;
+                let total = total.fetch_one(pool).await?;
+                Ok((items, total as u64))
+            })
+        })
+    }
+
+    fn get(&self, id: i64) -> BoxFuture<'_, Result<Option<Item>, sqlx::Error>> {
+        Box::pin(async move {
+            let sql = format!("SELECT {} FROM items WHERE id = $1", ITEM_COLUMNS);
+            with_pool!(self.database.pool(), |pool| sqlx::query_as::<_, Item>(&sql).bind(id).fetch_optional(pool).await)
+        })
+    }
+
+    fn create(&self, new: NewItem) -> BoxFuture<'_, Result<Item, sqlx::Error>> {
+        Box::pin(async move {
+            let sql = format!("INSERT INTO items (name, description) VALUES ($1, $2) RETURNING {}", ITEM_COLUMNS);
+            with_pool!(self.database.pool(), |pool| {
+                sqlx::query_as::<_, Item>(&sql).bind(&new.name).bind(&new.description).fetch_one(pool).await
+            })
+        })
+    }
+
+    fn replace(&self, id: i64, new: NewItem) -> BoxFuture<'_, Result<Option<Item>, sqlx::Error>> {
+        Box::pin(async move {
+            let sql = format!("UPDATE items SET name = $2, description = $3 WHERE id = $1 RETURNING {}", ITEM_COLUMNS);
+            with_pool!(self.database.pool(), |pool| {
+                sqlx::query_as::<_, Item>(&sql)
+                    .bind(id)
+                    .bind(&new.name)
+                    .bind(&new.description)
+                    .fetch_optional(pool)
+                    .await
+            })
+        })
+    }
+
+    fn update(&self, id: i64, patch: ItemPatch) -> BoxFuture<'_, Result<Option<Item>, sqlx::Error>> {
+        Box::pin(async move {
+            let sql = format!(
+                "UPDATE items SET name = COALESCE($2, name), description = COALESCE($3, description) WHERE id = $1 RETURNING {}",
+                ITEM_COLUMNS
+            );
+            with_pool!(self.database.pool(), |pool| {
+                sqlx::query_as::<_, Item>(&sql)
+                    .bind(id)
+                    .bind(&patch.name)
+                    .bind(&patch.description)
+                    .fetch_optional(pool)
+                    .await
+            })
+        })
+    }
+
+    fn delete(&self, id: i64) -> BoxFuture<'_, Result<bool, sqlx::Error>> {
+        Box::pin(async move {
+            with_pool!(self.database.pool(), |pool| {
+                let result = sqlx::query("DELETE FROM items WHERE id = $1").bind(id).execute(pool).await?;
+                Ok(result.rows_affected() > 0)
+            })
+        })
+    }
+}
@@ -0,0 +1,171 @@
+// response_headers.rs
+use crate::config::{ResponseHeaderRule, ResponseHeadersConfig};
+use crate::logging::RequestId;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use actix_web::web::Data;
+use actix_web::{Error, HttpMessage};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::io;
+use tracing::warn;
+
+// Reglas de cabeceras de respuesta ya compiladas
+pub struct ResponseHeaders {
+    rules: Vec<HeaderRule>,
+}
+
+struct HeaderRule {
+    // None = cualquier ruta
+    path: Option<Regex>,
+    methods: Vec<Method>,
+    add: Vec<(HeaderName, String)>,
+    set: Vec<(HeaderName, String)>,
+    remove: Vec<HeaderName>,
+}
+
+impl ResponseHeaders {
+    pub fn new(config: &ResponseHeadersConfig) -> io::Result<Self> {
+        let rules = config.rules.iter().map(HeaderRule::new).collect::<io::Result<_>>()?;
+        Ok(ResponseHeaders { rules })
+    }
+
+    fn matching(&self, method: &Method, path: &str) -> Vec<&HeaderRule> {
+        self.rules.iter().filter(|rule| rule.matches(method, path)).collect()
+    }
+}
+
+impl HeaderRule {
+    fn new(config: &ResponseHeaderRule) -> io::Result<Self> {
+        let pattern = match (&config.path, &config.path_pattern) {
+            (Some(_), Some(_)) => {
+                return Err(invalid("una regla no puede tener path y path_pattern a la vez".to_string()));
+            }
+            (Some(glob), None) => Some(glob_to_regex(glob)),
+            (None, Some(pattern)) => Some(format!("^(?:{})$", pattern)),
+            (None, None) => None,
+        };
+        let path = pattern
+            .map(|pattern| Regex::new(&pattern).map_err(|e| invalid(format!("patrón de ruta inválido: {}", e))))
+            .transpose()?;
+
+        let methods = config
+            .methods
+            .iter()
+            .map(|method| {
+                Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+                    .map_err(|_| invalid(format!("método inválido: {}", method)))
+            })
+            .collect::<io::Result<_>>()?;
+
+        Ok(HeaderRule {
+            path,
+            methods,
+            add: header_values(&config.add)?,
+            set: header_values(&config.set)?,
+            remove: config.remove.iter().map(|name| header_name(name)).collect::<io::Result<_>>()?,
+        })
+    }
+
+    fn matches(&self, method: &Method, path: &str) -> bool {
+        (self.methods.is_empty() || self.methods.contains(method))
+            && self.path.as_ref().is_none_or(|pattern| pattern.is_match(path))
+    }
+}
+
+fn header_values(values: &BTreeMap<String, String>) -> io::Result<Vec<(HeaderName, String)>> {
+    values.iter().map(|(name, value)| Ok((header_name(name)?, value.clone()))).collect()
+}
+
+fn header_name(name: &str) -> io::Result<HeaderName> {
+    HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid(format!("nombre de cabecera inválido: {}", name)))
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("Cabeceras de respuesta: {}", message))
+}
+
+// "*" cualquier cosa salvo "/", "**" cualquier cosa, "?" un carácter salvo "/"
+fn glob_to_regex(glob: &str) -> String {
+    let mut pattern = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                pattern.push_str(".*");
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    pattern
+}
+
+// Valores que se pueden usar en las plantillas de las cabeceras
+struct TemplateValues {
+    request_id: String,
+    method: String,
+    path: String,
+}
+
+impl TemplateValues {
+    fn expand(&self, value: &str) -> String {
+        if !value.contains('{') {
+            return value.to_string();
+        }
+        value
+            .replace("{request_id}", &self.request_id)
+            .replace("{method}", &self.method)
+            .replace("{path}", &self.path)
+    }
+}
+
+// Se aplica sobre la respuesta final, después de los handlers y del resto de middlewares
+pub async fn response_headers_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let rules = match req.app_data::<Data<ResponseHeaders>>() {
+        Some(headers) if !headers.rules.is_empty() => headers.clone(),
+        _ => return next.call(req).await,
+    };
+    let matching = rules.matching(req.method(), req.path());
+    if matching.is_empty() {
+        return next.call(req).await;
+    }
+
+    let values = TemplateValues {
+        request_id: req.extensions().get::<RequestId>().map(|id| id.0.clone()).unwrap_or_default(),
+        method: req.method().to_string(),
+        path: req.path().to_string(),
+    };
+    let mut res = next.call(req).await?;
+
+    let headers = res.headers_mut();
+    for rule in matching {
+        for name in &rule.remove {
+            headers.remove(name);
+        }
+        for (name, value) in &rule.set {
+            match HeaderValue::from_str(&values.expand(value)) {
+                Ok(value) => {
+                    headers.insert(name.clone(), value);
+                }
+                Err(e) => warn!("Valor inválido para la cabecera {}: {}", name, e),
+            }
+        }
+        for (name, value) in &rule.add {
+            match HeaderValue::from_str(&values.expand(value)) {
+                Ok(value) => headers.append(name.clone(), value),
+                Err(e) => warn!("Valor inválido para la cabecera {}: {}", name, e),
+            }
+        }
+    }
+    Ok(res)
+}
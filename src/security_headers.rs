@@ -0,0 +1,125 @@
+// security_headers.rs
+use crate::config::{SecurityHeadersConfig, SecurityPolicy};
+use crate::html_template;
+use crate::paths;
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::web::Data;
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest};
+use rand::distributions::Alphanumeric;
+use rand::rngs::OsRng;
+use rand::Rng;
+use std::future::{ready, Ready};
+use tracing::warn;
+
+// Marcador que se sustituye por el nonce en la CSP y en el HTML servido
+const CSP_NONCE_PLACEHOLDER: &str = "{nonce}";
+const HTML_NONCE_PLACEHOLDER: &str = "{{csp_nonce}}";
+
+// Cabeceras de seguridad ya resueltas para cada prefijo de ruta
+pub struct SecurityHeaders {
+    enabled: bool,
+    default: Vec<(HeaderName, String)>,
+    routes: Vec<(String, Vec<(HeaderName, String)>)>,
+}
+
+impl SecurityHeaders {
+    pub fn new(config: &SecurityHeadersConfig) -> Self {
+        let mut routes: Vec<_> = config
+            .routes
+            .iter()
+            .map(|route| {
+                let policy = config.default.merge(&route.policy);
+                (route.path_prefix.clone(), header_list(&policy))
+            })
+            .collect();
+        // El prefijo más largo tiene prioridad
+        routes.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+
+        SecurityHeaders {
+            enabled: config.enabled,
+            default: header_list(&config.default),
+            routes,
+        }
+    }
+
+    fn headers_for(&self, path: &str) -> &[(HeaderName, String)] {
+        self.routes
+            .iter()
+            .find(|(prefix, _)| paths::matches_prefix(path, prefix))
+            .map(|(_, headers)| headers.as_slice())
+            .unwrap_or(&self.default)
+    }
+}
+
+fn header_list(policy: &SecurityPolicy) -> Vec<(HeaderName, String)> {
+    [
+        (header::STRICT_TRANSPORT_SECURITY, &policy.strict_transport_security),
+        (header::CONTENT_SECURITY_POLICY, &policy.content_security_policy),
+        (header::X_FRAME_OPTIONS, &policy.x_frame_options),
+        (header::X_CONTENT_TYPE_OPTIONS, &policy.x_content_type_options),
+        (header::REFERRER_POLICY, &policy.referrer_policy),
+        (HeaderName::from_static("permissions-policy"), &policy.permissions_policy),
+        (HeaderName::from_static("cross-origin-opener-policy"), &policy.cross_origin_opener_policy),
+        (HeaderName::from_static("cross-origin-embedder-policy"), &policy.cross_origin_embedder_policy),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| match value.as_deref() {
+        Some(value) if !value.is_empty() => Some((name, value.to_string())),
+        _ => None,
+    })
+    .collect()
+}
+
+// Nonce CSP de la respuesta actual, disponible para los handlers
+#[derive(Debug, Clone)]
+pub struct CspNonce(pub String);
+
+impl FromRequest for CspNonce {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let nonce = req.extensions().get::<CspNonce>().cloned();
+        ready(nonce.ok_or_else(|| actix_web::error::ErrorInternalServerError("Nonce CSP no disponible")))
+    }
+}
+
+pub async fn security_headers_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let security = match req.app_data::<Data<SecurityHeaders>>() {
+        Some(security) if security.enabled => security.clone(),
+        _ => return Ok(next.call(req).await?.map_into_boxed_body()),
+    };
+
+    let nonce: String = OsRng.sample_iter(&Alphanumeric).take(22).map(char::from).collect();
+    req.extensions_mut().insert(CspNonce(nonce.clone()));
+    let path = paths::route_path(&req).to_string();
+
+    let mut res = next.call(req).await?.map_into_boxed_body();
+    let headers = security.headers_for(&path);
+
+    let mut uses_nonce = false;
+    for (name, value) in headers {
+        // No se pisan las cabeceras que el handler haya fijado explícitamente
+        if res.headers().contains_key(name) {
+            continue;
+        }
+        uses_nonce |= value.contains(CSP_NONCE_PLACEHOLDER);
+        match HeaderValue::from_str(&value.replace(CSP_NONCE_PLACEHOLDER, &nonce)) {
+            Ok(value) => {
+                res.headers_mut().insert(name.clone(), value);
+            }
+            Err(e) => warn!("Valor inválido para la cabecera {}: {}", name, e),
+        }
+    }
+
+    if uses_nonce && html_template::is_html(&res) {
+        res = html_template::replace_placeholder(res, HTML_NONCE_PLACEHOLDER, || Some(nonce)).await?;
+    }
+    Ok(res)
+}
@@ -0,0 +1,198 @@
+// server.rs
+use crate::admin::{self, AdminState};
+use crate::app::{self, AppState};
+use crate::config::{Config, ListenerConfig, ListenerKind};
+use crate::connections::ConnectionTracker;
+use crate::dos::DosGuard;
+use crate::lifecycle::{ControlSignal, Reloader, Signals};
+use crate::mtls;
+use crate::tls::{self, CertStore, HttpsRedirect};
+use actix_web::dev::{Server, ServerHandle};
+use actix_web::web::{self, Data};
+use actix_web::{App, HttpServer};
+use futures_util::future;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::io;
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+
+// Aplica los ajustes propios de cada listener y lo enlaza según su tipo
+macro_rules! bind_listener {
+    ($server:expr, $listener:expr, $cert_store:expr, $config:expr, $connections:expr, $dos_guard:expr) => {{
+        let listener: &ListenerConfig = $listener;
+        let connections: Data<ConnectionTracker> = $connections.clone();
+        let dos_guard: Data<DosGuard> = $dos_guard.clone();
+        let listener_name = listener.name.clone();
+        connections.set_capacity(&listener.name, listener.max_connections);
+
+        let server = $server
+            // Las señales las gestiona run() para coordinar todos los listeners
+            .disable_signals()
+            .shutdown_timeout($config.server.shutdown_timeout_secs)
+            .workers(listener.workers.max(1))
+            // actix lo aplica por worker; el máximo del listener lo controla ConnectionTracker
+            .max_connections(listener.max_connections)
+            .max_connection_rate(listener.max_connection_rate)
+            .keep_alive(Duration::from_secs(listener.keep_alive_secs))
+            .client_request_timeout(Duration::from_secs(listener.client_request_timeout_secs))
+            .client_disconnect_timeout(Duration::from_secs(listener.client_disconnect_timeout_secs))
+            .on_connect(move |connection, data| {
+                if let Some(reason) = DosGuard::on_connect(&dos_guard, connection, data) {
+                    connections.reject(&listener_name, reason);
+                    return;
+                }
+                mtls::on_connect(connection, data);
+                ConnectionTracker::on_connect(&connections, &listener_name, connection, data);
+            });
+
+        match listener.kind {
+            ListenerKind::Http if listener.reuse_port => server.listen(reuse_port_listener(&listener.bind)?)?,
+            ListenerKind::Http => server.bind(&listener.bind)?,
+            ListenerKind::Https => {
+                let store = $cert_store.clone().ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "Listener HTTPS sin certificados cargados")
+                })?;
+                let client_verifier = mtls::client_verifier(listener.client_auth, &$config.mtls)?;
+                let resolver = $connections.counting_resolver(&listener.name, store);
+                let tls_config = tls::server_config(resolver, client_verifier)?;
+                if listener.reuse_port {
+                    server.listen_rustls_0_23(reuse_port_listener(&listener.bind)?, tls_config)?
+                } else {
+                    server.bind_rustls_0_23(&listener.bind, tls_config)?
+                }
+            }
+            #[cfg(unix)]
+            ListenerKind::Unix => {
+                remove_stale_socket(&listener.bind)?;
+                server.bind_uds(&listener.bind)?
+            }
+            #[cfg(not(unix))]
+            ListenerKind::Unix => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "Los sockets Unix no están disponibles en esta plataforma",
+                ))
+            }
+        }
+    }};
+}
+
+// Socket TCP con SO_REUSEPORT: varios procesos pueden escuchar en el mismo puerto
+// y el kernel reparte las conexiones. Permite arrancar un binario nuevo y luego
+// detener el anterior sin rechazar conexiones.
+pub fn reuse_port_listener(bind: &str) -> io::Result<TcpListener> {
+    let addr: SocketAddr = bind
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("Dirección inválida: {}", bind)))?;
+
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    socket.set_reuse_address(true)?;
+    #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+    socket.set_reuse_port(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(1024)?;
+    socket.set_nonblocking(true)?;
+    Ok(socket.into())
+}
+
+// Un socket de una ejecución anterior impediría el bind
+#[cfg(unix)]
+fn remove_stale_socket(path: &str) -> io::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    match std::fs::metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(path),
+        _ => Ok(()),
+    }
+}
+
+// Arranca un servidor por listener y espera a que terminen todos. Si uno se
+// detiene (o falla), o llega SIGTERM/SIGINT, se paran todos esperando a que
+// terminen las peticiones en curso. SIGHUP recarga lo que no requiere reiniciar.
+pub async fn run(
+    config: &Config,
+    state: AppState,
+    cert_store: Option<Arc<CertStore>>,
+    reloader: &Reloader,
+) -> io::Result<()> {
+    let https_redirect = Data::new(HttpsRedirect::new(&config.tls, &config.server.listeners));
+    let connections = state.connections.clone();
+    let health = state.health.clone();
+    let dos_guard = state.dos_guard.clone();
+    let admin_state = AdminState::new(config, state.metrics.clone(), connections.clone(), health.clone(), state.access.clone());
+    let mut servers: Vec<Server> = Vec::new();
+
+    for listener in &config.server.listeners {
+        let server = if listener.admin {
+            let admin_state = admin_state.clone();
+            let server = HttpServer::new(move || admin::build_admin_app(&admin_state));
+            bind_listener!(server, listener, cert_store, config, connections, dos_guard).run()
+        } else if listener.kind == ListenerKind::Http && listener.redirect_to_https {
+            let https_redirect = https_redirect.clone();
+            let trusted_proxies = state.trusted_proxies.clone();
+            let server = HttpServer::new(move || {
+                App::new()
+                    .app_data(https_redirect.clone())
+                    .app_data(trusted_proxies.clone())
+                    .route("/.well-known/acme-challenge/{token}", web::get().to(tls::acme_challenge))
+                    .default_service(web::to(tls::https_redirect))
+            });
+            bind_listener!(server, listener, cert_store, config, connections, dos_guard).run()
+        } else {
+            let state = state.clone();
+            let server = HttpServer::new(move || app::build_app(&state));
+            bind_listener!(server, listener, cert_store, config, connections, dos_guard).run()
+        };
+
+        info!("Listener '{}' ({:?}) escuchando en {}", listener.name, listener.kind, listener.bind);
+        servers.push(server);
+    }
+
+    if servers.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "No hay listeners configurados"));
+    }
+
+    let mut signals = Signals::new()?;
+    let handles: Vec<ServerHandle> = servers.iter().map(Server::handle).collect();
+    let mut running = future::select_all(servers.into_iter().map(tokio::spawn));
+    health.set_started();
+
+    let finished = loop {
+        tokio::select! {
+            (result, _, remaining) = &mut running => break Some((result, remaining)),
+            signal = signals.recv() => match signal {
+                ControlSignal::Reload => reloader.reload().await,
+                ControlSignal::Shutdown => break None,
+            },
+        }
+    };
+    health.begin_shutdown();
+    let (first, remaining) = match finished {
+        Some((result, remaining)) => (Some(result), remaining),
+        None => {
+            // /readyz ya falla; se sigue atendiendo mientras el balanceador lo detecta
+            if config.server.shutdown_delay_secs > 0 {
+                info!("Parada en {}s: /readyz devuelve 503", config.server.shutdown_delay_secs);
+                tokio::time::sleep(Duration::from_secs(config.server.shutdown_delay_secs)).await;
+            }
+            (None, running.into_inner())
+        }
+    };
+
+    info!(
+        "Deteniendo listeners (esperando las peticiones en curso, máximo {}s)",
+        config.server.shutdown_timeout_secs
+    );
+    future::join_all(handles.iter().map(|handle| handle.stop(true))).await;
+
+    for task in remaining {
+        task.await.map_err(io::Error::other)??;
+    }
+    match first {
+        Some(result) => result.map_err(io::Error::other)?,
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,110 @@
+// telemetry.rs
+use crate::config::TracingConfig;
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+use opentelemetry::propagation::{Extractor, Injector};
+use opentelemetry::trace::{TraceContextExt, TracerProvider as _};
+use opentelemetry::{global, Context};
+use opentelemetry_otlp::{Protocol, SpanExporter, WithExportConfig, WithHttpConfig};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Sampler, SdkTracer, SdkTracerProvider};
+use opentelemetry_sdk::Resource;
+use std::io;
+use std::time::Duration;
+use tracing::{warn, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+// Proveedor de trazas activo; al detener el servidor se vacía el lote pendiente
+#[derive(Default)]
+pub struct Telemetry {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Telemetry {
+    // Crea el exportador OTLP e instala el propagador W3C (traceparent / tracestate).
+    // Sin exportación configurada no hace nada y las cabeceras se ignoran.
+    pub fn new(config: &TracingConfig) -> io::Result<Self> {
+        if !config.enabled {
+            return Ok(Telemetry::default());
+        }
+
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_protocol(Protocol::HttpBinary)
+            .with_endpoint(config.endpoint.clone())
+            .with_timeout(Duration::from_secs(config.export_timeout_secs))
+            .with_headers(config.headers.clone().into_iter().collect())
+            .build()
+            .map_err(|e| io::Error::other(format!("Exportador OTLP '{}': {}", config.endpoint, e)))?;
+
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_sampler(sampler(config))
+            .with_resource(Resource::builder().with_service_name(config.service_name.clone()).build())
+            .build();
+
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        Ok(Telemetry { provider: Some(provider) })
+    }
+
+    pub fn tracer(&self) -> Option<SdkTracer> {
+        self.provider.as_ref().map(|provider| provider.tracer("servidor"))
+    }
+
+    // Exporta los spans pendientes. Bloquea hasta que termina el envío
+    pub fn shutdown(&self) {
+        if let Some(provider) = &self.provider {
+            if let Err(e) = provider.shutdown() {
+                warn!("Error al exportar las trazas pendientes: {}", e);
+            }
+        }
+    }
+}
+
+fn sampler(config: &TracingConfig) -> Sampler {
+    let ratio = Sampler::TraceIdRatioBased(config.sample_ratio.clamp(0.0, 1.0));
+    if config.parent_based {
+        Sampler::ParentBased(Box::new(ratio))
+    } else {
+        ratio
+    }
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (HeaderName::try_from(key), HeaderValue::try_from(value)) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+// Continúa la traza del cliente si la petición trae traceparent
+pub fn set_remote_parent(span: &Span, headers: &HeaderMap) {
+    let parent = global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(headers)));
+    // Solo falla si el span no se está exportando
+    let _ = span.set_parent(parent);
+}
+
+// Cambia el nombre del span exportado (una vez iniciado, "otel.name" ya no se aplica)
+pub fn set_span_name(span: &Span, name: String) {
+    span.context().span().update_name(name);
+}
+
+// Añade traceparent/tracestate del span a las cabeceras (respuesta o petición saliente)
+pub fn inject_context(span: &Span, headers: &mut HeaderMap) {
+    let context: Context = span.context();
+    global::get_text_map_propagator(|propagator| propagator.inject_context(&context, &mut HeaderInjector(headers)));
+}
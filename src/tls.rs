@@ -0,0 +1,226 @@
+// tls.rs
+use crate::client_ip::RequestOrigin;
+use crate::config::{CertificateConfig, ListenerConfig, ListenerKind, TlsConfig};
+use crate::error::AppError;
+use crate::file_watch;
+use actix_web::http::header;
+use actix_web::{web, HttpRequest, HttpResponse, ResponseError};
+use rustls::crypto::ring;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::danger::ClientCertVerifier;
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use rustls::ServerConfig;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{error, info};
+
+#[derive(Debug)]
+struct LoadedCertificate {
+    // Nombres SNI ("example.com", "*.example.com"); vacío = certificado por defecto
+    server_names: Vec<String>,
+    key: Arc<CertifiedKey>,
+}
+
+// Almacén de certificados con selección por SNI. Se puede recargar en caliente:
+// las conexiones abiertas siguen con su sesión y los nuevos handshakes usan el certificado nuevo.
+#[derive(Debug)]
+pub struct CertStore {
+    sources: RwLock<Vec<CertificateConfig>>,
+    certificates: RwLock<Vec<LoadedCertificate>>,
+}
+
+impl CertStore {
+    pub fn load(sources: &[CertificateConfig]) -> io::Result<Self> {
+        if sources.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Listener HTTPS sin certificados configurados"));
+        }
+
+        Ok(CertStore {
+            certificates: RwLock::new(load_all(sources)?),
+            sources: RwLock::new(sources.to_vec()),
+        })
+    }
+
+    // Vuelve a leer todos los certificados; si alguno falla se mantienen los actuales
+    pub fn reload(&self) -> io::Result<()> {
+        let sources = self.sources.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+        self.replace(&sources)
+    }
+
+    // Carga un nuevo conjunto de certificados (por ejemplo, tras recargar la configuración)
+    pub fn replace(&self, sources: &[CertificateConfig]) -> io::Result<()> {
+        if sources.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Listener HTTPS sin certificados configurados"));
+        }
+
+        let certificates = load_all(sources)?;
+        *self.certificates.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = certificates;
+        *self.sources.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = sources.to_vec();
+        info!("Certificados TLS recargados");
+        Ok(())
+    }
+
+    // Rutas de los archivos PEM a vigilar
+    pub fn watched_files(&self) -> Vec<PathBuf> {
+        self.sources
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .flat_map(|source| [PathBuf::from(&source.cert_path), PathBuf::from(&source.key_path)])
+            .collect()
+    }
+}
+
+impl ResolvesServerCert for CertStore {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        let certificates = self.certificates.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        if let Some(name) = client_hello.server_name() {
+            let name = name.to_ascii_lowercase();
+            let found = certificates
+                .iter()
+                .find(|cert| cert.server_names.iter().any(|pattern| server_name_matches(pattern, &name)));
+            if let Some(cert) = found {
+                return Some(cert.key.clone());
+            }
+        }
+
+        // Sin SNI o sin coincidencia: el primero sin nombres, o el primero de la lista
+        certificates
+            .iter()
+            .find(|cert| cert.server_names.is_empty())
+            .or_else(|| certificates.first())
+            .map(|cert| cert.key.clone())
+    }
+}
+
+fn server_name_matches(pattern: &str, name: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        // El comodín cubre exactamente una etiqueta
+        Some(suffix) => name
+            .strip_suffix(suffix)
+            .and_then(|label| label.strip_suffix('.'))
+            .is_some_and(|label| !label.is_empty() && !label.contains('.')),
+        None => pattern.eq_ignore_ascii_case(name),
+    }
+}
+
+fn load_all(sources: &[CertificateConfig]) -> io::Result<Vec<LoadedCertificate>> {
+    sources
+        .iter()
+        .map(|source| {
+            Ok(LoadedCertificate {
+                server_names: source.server_names.iter().map(|name| name.to_ascii_lowercase()).collect(),
+                key: Arc::new(load_certified_key(&source.cert_path, &source.key_path)?),
+            })
+        })
+        .collect()
+}
+
+fn load_certified_key(cert_path: &str, key_path: &str) -> io::Result<CertifiedKey> {
+    let invalid = |path: &str, e: &dyn std::fmt::Display| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("Error al leer '{}': {}", path, e))
+    };
+
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .map_err(|e| invalid(cert_path, &e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| invalid(cert_path, &e))?;
+    if certs.is_empty() {
+        return Err(invalid(cert_path, &"no contiene certificados"));
+    }
+
+    let key = PrivateKeyDer::from_pem_file(key_path).map_err(|e| invalid(key_path, &e))?;
+    let signing_key = ring::sign::any_supported_type(&key).map_err(|e| invalid(key_path, &e))?;
+
+    Ok(CertifiedKey::new(certs, signing_key))
+}
+
+// Configuración rustls con el resolvedor de certificados indicado (normalmente el CertStore).
+// actix-web añade por su cuenta los protocolos ALPN "h2" y "http/1.1".
+pub fn server_config(
+    resolver: Arc<dyn ResolvesServerCert>,
+    client_verifier: Option<Arc<dyn ClientCertVerifier>>,
+) -> io::Result<ServerConfig> {
+    let builder = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let config = match client_verifier {
+        Some(verifier) => builder.with_client_cert_verifier(verifier),
+        None => builder.with_no_client_auth(),
+    };
+    Ok(config.with_cert_resolver(resolver))
+}
+
+// Monitorear cambios en los certificados y recargarlos
+pub async fn monitor_certificates(store: Arc<CertStore>) -> io::Result<()> {
+    // Esperar a que termine de escribirse el par certificado/clave
+    file_watch::watch_files("certificados", &store.watched_files(), Duration::from_millis(500), || {
+        if let Err(e) = store.reload() {
+            error!("Error al recargar los certificados TLS (se mantienen los anteriores): {}", e);
+        }
+    })
+    .await
+}
+
+// Datos para la redirección HTTP -> HTTPS
+pub struct HttpsRedirect {
+    pub https_port: u16,
+    pub acme_challenge_dir: PathBuf,
+}
+
+impl HttpsRedirect {
+    // El puerto de destino es el del primer listener HTTPS
+    pub fn new(config: &TlsConfig, listeners: &[ListenerConfig]) -> Self {
+        let https_port = listeners
+            .iter()
+            .find(|listener| listener.kind == ListenerKind::Https)
+            .and_then(|listener| listener.bind.rsplit_once(':'))
+            .and_then(|(_, port)| port.parse().ok())
+            .unwrap_or(443);
+
+        HttpsRedirect {
+            https_port,
+            acme_challenge_dir: PathBuf::from(&config.acme_challenge_dir),
+        }
+    }
+}
+
+// GET /.well-known/acme-challenge/{token}: respuestas de validación ACME desde disco
+pub async fn acme_challenge(redirect: web::Data<HttpsRedirect>, token: web::Path<String>) -> HttpResponse {
+    let token = token.into_inner();
+    let valid = !token.is_empty() && token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return AppError::NotFound.error_response();
+    }
+
+    match tokio::fs::read(redirect.acme_challenge_dir.join(&token)).await {
+        Ok(content) => HttpResponse::Ok().content_type("text/plain").body(content),
+        Err(_) => AppError::NotFound.error_response(),
+    }
+}
+
+// Cualquier otra petición HTTP se redirige de forma permanente a HTTPS
+pub async fn https_redirect(redirect: web::Data<HttpsRedirect>, req: HttpRequest) -> HttpResponse {
+    let host = RequestOrigin::from_request_parts(&req).host;
+    let host = match host.rsplit_once(':') {
+        // Se descarta el puerto salvo en direcciones IPv6 sin puerto ("[::1]")
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name.to_string(),
+        _ => host,
+    };
+
+    let authority = if redirect.https_port == 443 {
+        host
+    } else {
+        format!("{}:{}", host, redirect.https_port)
+    };
+    let path = req.uri().path_and_query().map(|pq| pq.as_str()).unwrap_or("/");
+
+    HttpResponse::MovedPermanently()
+        .append_header((header::LOCATION, format!("https://{}{}", authority, path)))
+        .finish()
+}
@@ -8,7 +8,7 @@ async function fetchItems() {
 
   try {
     // Solicitud al servidor para obtener los datos
-    const response = await fetch('/items', {
+    const response = await fetch('/api/items', {
       method: 'GET',
       headers: {
         'Authorization': `Bearer ${token}`,
@@ -17,7 +17,7 @@ async function fetchItems() {
 
     if (response.ok) {
       // Procesar y mostrar los datos
-      const items = await response.json();
+      const { items } = await response.json();
       const table = document.getElementById('items-table').getElementsByTagName('tbody')[0];
       table.innerHTML = ''; // Limpiar contenido previo
 
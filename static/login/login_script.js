@@ -4,13 +4,15 @@ document.getElementById('login-form').addEventListener('submit', async function
     const username = document.getElementById('username').value;
     const password = document.getElementById('password').value;
     const errorMessage = document.getElementById('error-message');
+    const csrfToken = document.querySelector('meta[name="csrf-token"]').content;
 
     try {
         // Enviar credenciales al servidor
-        const response = await fetch('static/login', {
+        const response = await fetch('/login', {
             method: 'POST',
             headers: {
                 'Content-Type': 'application/json',
+                'X-CSRF-Token': csrfToken,
             },
             body: JSON.stringify({ username, password }),
         });
@@ -48,7 +50,7 @@ window.onload = async function () {
     }
 
     try {
-        const response = await fetch('/items', {
+        const response = await fetch('/api/items?per_page=1', {
             method: 'GET',
             headers: {
                 'Authorization': `Bearer ${authToken}`, // Puedes incluir un token adicional si lo deseas
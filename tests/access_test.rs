@@ -0,0 +1,91 @@
+// tests/access_test.rs
+use actix_web::{middleware, test, web, App, HttpResponse};
+use servidor::access::{self, AccessControl};
+use servidor::config::{AccessConfig, AccessRule};
+use std::fs;
+
+#[actix_web::test]
+async fn test_access_rules_by_prefix() {
+    let config = AccessConfig {
+        rules: vec![
+            AccessRule {
+                deny: vec!["203.0.113.0/24".parse().unwrap(), "2001:db8:bad::/48".parse().unwrap()],
+                ..AccessRule::default()
+            },
+            AccessRule {
+                path_prefix: "/privado".to_string(),
+                allow: vec!["10.0.0.0/8".parse().unwrap(), "2001:db8:10::/48".parse().unwrap()],
+                ..AccessRule::default()
+            },
+        ],
+    };
+    let access = web::Data::new(AccessControl::new(&config).unwrap());
+
+    let app = test::init_service(
+        App::new()
+            .app_data(access.clone())
+            .wrap(middleware::from_fn(access::access_middleware))
+            .default_service(web::to(HttpResponse::Ok)),
+    )
+    .await;
+    let status = |uri: &'static str, peer: &'static str| {
+        let app = &app;
+        async move {
+            let req = test::TestRequest::get().uri(uri).peer_addr(peer.parse().unwrap()).to_request();
+            test::call_service(app, req).await.status().as_u16()
+        }
+    };
+
+    // La denegación se aplica a todas las rutas
+    assert_eq!(status("/", "198.51.100.1:1234").await, 200);
+    assert_eq!(status("/", "203.0.113.9:1234").await, 403);
+    assert_eq!(status("/", "[2001:db8:bad::1]:1234").await, 403);
+
+    // En /privado solo las redes permitidas
+    assert_eq!(status("/privado/panel", "10.1.2.3:1234").await, 200);
+    assert_eq!(status("/privado/panel", "[2001:db8:10::5]:1234").await, 200);
+    assert_eq!(status("/privado/panel", "198.51.100.1:1234").await, 403);
+    // La regla se compara con la ruta decodificada y por segmentos completos
+    assert_eq!(status("/privad%6f/panel", "198.51.100.1:1234").await, 403);
+    assert_eq!(status("/privadoX", "198.51.100.1:1234").await, 200);
+
+    // Sin IP (socket Unix) no se aplican las listas
+    let res = test::call_service(&app, test::TestRequest::get().uri("/privado").to_request()).await;
+    assert_eq!(res.status(), 200);
+}
+
+#[actix_web::test]
+async fn test_access_list_files_reload() {
+    let dir = std::env::temp_dir().join(format!("access_test_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let allow_path = dir.join("permitidas.txt");
+    fs::write(&allow_path, "# oficina\n192.168.10.0/24\n2001:db8::1  # VPN\n").unwrap();
+
+    let config = AccessConfig {
+        rules: vec![AccessRule {
+            path_prefix: "/admin".to_string(),
+            allow_file: Some(allow_path.to_string_lossy().into_owned()),
+            ..AccessRule::default()
+        }],
+    };
+    let access = AccessControl::new(&config).unwrap();
+    assert_eq!(access.watched_files(), vec![allow_path.clone()]);
+    assert!(access.allows("192.168.10.7".parse().unwrap(), "/admin"));
+    assert!(access.allows("2001:db8::1".parse().unwrap(), "/admin"));
+    assert!(!access.allows("192.168.11.7".parse().unwrap(), "/admin"));
+    assert!(access.allows("192.168.11.7".parse().unwrap(), "/"));
+
+    fs::write(&allow_path, "192.168.11.0/24\n").unwrap();
+    access.reload().unwrap();
+    assert!(access.allows("192.168.11.7".parse().unwrap(), "/admin"));
+    assert!(!access.allows("192.168.10.7".parse().unwrap(), "/admin"));
+
+    // Una lista vacía o inválida no se aplica: se mantiene la anterior
+    fs::write(&allow_path, "# nada\n").unwrap();
+    assert!(access.reload().is_err());
+    fs::write(&allow_path, "no-es-una-red\n").unwrap();
+    assert!(access.reload().is_err());
+    assert!(access.allows("192.168.11.7".parse().unwrap(), "/admin"));
+
+    fs::remove_dir_all(&dir).ok();
+}
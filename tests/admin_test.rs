@@ -0,0 +1,106 @@
+// tests/admin_test.rs
+use actix_web::{test, web, HttpMessage};
+use argon2::Params;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use servidor::access::AccessControl;
+use servidor::admin::{self, AdminState};
+use servidor::config::{Config, MtlsIdentityRule, UserCredentials};
+use servidor::connections::ConnectionTracker;
+use servidor::health::Health;
+use servidor::login::hash_password_with;
+use servidor::metrics::Metrics;
+use servidor::mtls::PeerCertificate;
+use std::sync::Arc;
+
+fn admin_state() -> AdminState {
+    let mut config = Config::default();
+    config.admin.allowed_ips = vec!["10.0.0.0/8".parse().unwrap()];
+    config.admin.users = vec![UserCredentials {
+        username: "prometheus".to_string(),
+        password_hash: hash_password_with("secreto", Params::new(8, 1, 1, None).unwrap()).unwrap(),
+    }];
+    config.admin.client_cert_roles = vec!["metrics".to_string()];
+    config.mtls.identities = vec![MtlsIdentityRule {
+        common_name: Some("monitorizacion".to_string()),
+        san: None,
+        roles: vec!["metrics".to_string()],
+    }];
+
+    let registry = Arc::new(prometheus::Registry::new());
+    let connections = ConnectionTracker::new(&registry).unwrap();
+    let metrics = Metrics::new(registry, &config.metrics);
+    metrics.http_requests_total.with_label_values(&["GET", "/", "2xx"]).inc();
+    AdminState::new(
+        &config,
+        web::Data::new(metrics),
+        web::Data::new(connections),
+        web::Data::new(Health::default()),
+        web::Data::new(AccessControl::new(&config.access).unwrap()),
+    )
+}
+
+fn metrics_request(peer: &str) -> test::TestRequest {
+    test::TestRequest::get().uri("/metrics").peer_addr(peer.parse().unwrap())
+}
+
+#[actix_web::test]
+async fn test_metrics_access_control() {
+    let app = test::init_service(admin::build_admin_app(&admin_state())).await;
+
+    let res = test::call_service(&app, metrics_request("192.168.1.5:4000").to_request()).await;
+    assert_eq!(res.status(), 403);
+
+    let res = test::call_service(&app, metrics_request("10.1.2.3:4000").to_request()).await;
+    assert_eq!(res.status(), 401);
+    assert!(res.headers().contains_key("www-authenticate"));
+
+    let wrong = format!("Basic {}", STANDARD.encode("prometheus:otra"));
+    let req = metrics_request("10.1.2.3:4000").insert_header(("Authorization", wrong)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+
+    let valid = format!("Basic {}", STANDARD.encode("prometheus:secreto"));
+    let req = metrics_request("10.1.2.3:4000").insert_header(("Authorization", valid)).to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert!(String::from_utf8_lossy(&body).contains("http_requests_total{method=\"GET\",route=\"/\",status=\"2xx\"} 1"));
+}
+
+#[actix_web::test]
+async fn test_openmetrics_negotiation() {
+    let app = test::init_service(admin::build_admin_app(&admin_state())).await;
+
+    let req = metrics_request("10.1.2.3:4000")
+        .insert_header(("Authorization", format!("Basic {}", STANDARD.encode("prometheus:secreto"))))
+        .insert_header(("Accept", "application/openmetrics-text; version=1.0.0"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert!(res.headers().get("content-type").unwrap().to_str().unwrap().starts_with("application/openmetrics-text"));
+
+    let body = String::from_utf8(test::read_body(res).await.to_vec()).unwrap();
+    assert!(body.contains("# TYPE http_requests counter"));
+    assert!(body.contains("http_requests_total{method=\"GET\",route=\"/\",status=\"2xx\"} 1"));
+    assert!(body.ends_with("# EOF\n"));
+}
+
+#[actix_web::test]
+async fn test_client_certificate_replaces_basic_auth() {
+    let app = test::init_service(admin::build_admin_app(&admin_state())).await;
+    let with_certificate = |common_name: &str| {
+        let req = metrics_request("10.1.2.3:4000").to_request();
+        req.extensions_mut().insert(PeerCertificate {
+            subject: format!("CN={}", common_name),
+            common_name: Some(common_name.to_string()),
+            alt_names: Vec::new(),
+        });
+        req
+    };
+
+    // Con el rol no hace falta contraseña
+    let res = test::call_service(&app, with_certificate("monitorizacion")).await;
+    assert_eq!(res.status(), 200);
+
+    // Sin el rol se sigue pidiendo la autenticación básica
+    let res = test::call_service(&app, with_certificate("otro")).await;
+    assert_eq!(res.status(), 401);
+    assert!(res.headers().contains_key("www-authenticate"));
+}
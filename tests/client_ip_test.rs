@@ -0,0 +1,118 @@
+// tests/client_ip_test.rs
+use actix_web::{middleware, test, web, App, HttpResponse, Responder};
+use servidor::client_ip::{ClientIp, TrustedProxies};
+use servidor::config::RateLimitConfig;
+use servidor::rate_limit::{rate_limit_middleware, RateLimiters};
+
+async fn client_ip(ip: ClientIp) -> impl Responder {
+    HttpResponse::Ok().body(ip.to_string())
+}
+
+fn trusted() -> web::Data<TrustedProxies> {
+    web::Data::new(TrustedProxies::new(vec!["10.0.0.0/8".parse().unwrap()]))
+}
+
+#[actix_web::test]
+async fn test_forwarded_for_ignored_from_untrusted_peer() {
+    let app = test::init_service(
+        App::new().app_data(trusted()).route("/", web::get().to(client_ip))
+    ).await;
+
+    let req = test::TestRequest::get()
+        .uri("/")
+        .peer_addr("203.0.113.7:5000".parse().unwrap())
+        .insert_header(("X-Forwarded-For", "1.2.3.4"))
+        .to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert_eq!(body, "203.0.113.7");
+}
+
+#[actix_web::test]
+async fn test_forwarded_chain_skips_trusted_hops() {
+    let app = test::init_service(
+        App::new().app_data(trusted()).route("/", web::get().to(client_ip))
+    ).await;
+
+    // El cliente falsifica el primer salto; solo cuenta el primero no confiable por la derecha
+    let req = test::TestRequest::get()
+        .uri("/")
+        .peer_addr("10.0.0.2:5000".parse().unwrap())
+        .insert_header(("X-Forwarded-For", "6.6.6.6, 198.51.100.9, 10.0.0.1"))
+        .to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert_eq!(body, "198.51.100.9");
+
+    let req = test::TestRequest::get()
+        .uri("/")
+        .peer_addr("10.0.0.2:5000".parse().unwrap())
+        .insert_header(("Forwarded", r#"for="[2001:db8::17]:4711";proto=https, for=10.0.0.1"#))
+        .insert_header(("X-Forwarded-For", "6.6.6.6"))
+        .to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert_eq!(body, "2001:db8::17");
+
+    let req = test::TestRequest::get()
+        .uri("/")
+        .peer_addr("10.0.0.2:5000".parse().unwrap())
+        .insert_header(("X-Real-IP", "192.0.2.44"))
+        .to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert_eq!(body, "192.0.2.44");
+}
+
+#[actix_web::test]
+async fn test_rate_limit_per_client_ip() {
+    let limiters = web::Data::new(RateLimiters::new(&RateLimitConfig {
+        global_per_minute: 100,
+        per_client_per_minute: 1,
+    }));
+    let app = test::init_service(
+        App::new()
+            .app_data(trusted())
+            .app_data(limiters)
+            .wrap(middleware::from_fn(rate_limit_middleware))
+            .route("/", web::get().to(client_ip))
+    ).await;
+
+    let request = |forwarded_for: &str| {
+        test::TestRequest::get()
+            .uri("/")
+            .peer_addr("10.0.0.2:5000".parse().unwrap())
+            .insert_header(("X-Forwarded-For", forwarded_for.to_string()))
+            .to_request()
+    };
+
+    assert!(test::call_service(&app, request("198.51.100.1")).await.status().is_success());
+    assert_eq!(test::call_service(&app, request("198.51.100.1")).await.status(), 429);
+    assert!(test::call_service(&app, request("198.51.100.2")).await.status().is_success());
+}
+
+#[actix_web::test]
+async fn test_request_origin_trusts_forwarded_host_only_from_proxies() {
+    use servidor::client_ip::RequestOrigin;
+
+    async fn origin(req: actix_web::HttpRequest) -> impl Responder {
+        let origin = RequestOrigin::from_request_parts(&req);
+        HttpResponse::Ok().body(format!("{}://{}", origin.scheme, origin.host))
+    }
+
+    let app = test::init_service(
+        App::new().app_data(trusted()).route("/", web::get().to(origin))
+    ).await;
+
+    let forwarded = |peer: &str| {
+        test::TestRequest::get()
+            .uri("/")
+            .peer_addr(peer.parse().unwrap())
+            .insert_header(("Host", "ejemplo.com"))
+            .insert_header(("X-Forwarded-Host", "atacante.example"))
+            .insert_header(("X-Forwarded-Proto", "https"))
+            .to_request()
+    };
+
+    let body = test::call_and_read_body(&app, forwarded("203.0.113.7:5000")).await;
+    assert_eq!(body, "http://ejemplo.com");
+
+    let body = test::call_and_read_body(&app, forwarded("10.0.0.2:5000")).await;
+    assert_eq!(body, "https://atacante.example");
+}
@@ -0,0 +1,110 @@
+// tests/connections_test.rs
+use actix_web::web::{self, Data};
+use actix_web::{middleware, App, HttpResponse, HttpServer};
+use prometheus::Registry;
+use servidor::connections::{self, ConnectionTracker};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+#[actix_web::test]
+async fn test_tracks_open_connections() {
+    let registry = Registry::new();
+    let tracker = Data::new(ConnectionTracker::new(&registry).unwrap());
+
+    let on_connect_tracker = tracker.clone();
+    let server = HttpServer::new(|| {
+        App::new()
+            .wrap(middleware::from_fn(connections::connection_middleware))
+            .route("/", web::get().to(|| async { HttpResponse::Ok().body("hola") }))
+    })
+    .workers(1)
+    .on_connect(move |connection, data| ConnectionTracker::on_connect(&on_connect_tracker, "test", connection, data))
+    .bind("127.0.0.1:0")
+    .unwrap();
+    let addr = server.addrs()[0];
+    let server = server.run();
+    let handle = server.handle();
+    actix_web::rt::spawn(server);
+
+    // Dos peticiones por la misma conexión (keep-alive)
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let mut received = String::new();
+    for _ in 0..2 {
+        stream.write_all(b"GET / HTTP/1.1\r\nHost: test\r\n\r\n").await.unwrap();
+        let mut buffer = [0u8; 1024];
+        let n = stream.read(&mut buffer).await.unwrap();
+        received.push_str(&String::from_utf8_lossy(&buffer[..n]));
+    }
+    assert_eq!(received.matches("200 OK").count(), 2);
+
+    let open = tracker.snapshot();
+    assert_eq!(open.len(), 1);
+    assert_eq!(open[0].requests, 2);
+    assert_eq!(open[0].bytes_sent, 8);
+    assert!(open[0].peer.is_some());
+
+    let families = registry.gather();
+    let reused = families
+        .iter()
+        .find(|family| family.get_name() == "http_connection_requests_total")
+        .unwrap()
+        .get_metric()
+        .iter()
+        .find(|metric| metric.get_label().iter().any(|l| l.get_name() == "reused" && l.get_value() == "true"))
+        .unwrap();
+    assert_eq!(reused.get_counter().get_value(), 1.0);
+
+    // Al cerrar la conexión deja de aparecer
+    drop(stream);
+    for _ in 0..50 {
+        if tracker.snapshot().is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert!(tracker.snapshot().is_empty());
+
+    handle.stop(true).await;
+}
+
+#[actix_web::test]
+async fn test_rejects_connections_over_capacity() {
+    let registry = Registry::new();
+    let tracker = Data::new(ConnectionTracker::new(&registry).unwrap());
+    tracker.set_capacity("test", 1);
+
+    let on_connect_tracker = tracker.clone();
+    let server = HttpServer::new(|| App::new().route("/", web::get().to(HttpResponse::Ok)))
+        .workers(1)
+        .on_connect(move |connection, data| ConnectionTracker::on_connect(&on_connect_tracker, "test", connection, data))
+        .bind("127.0.0.1:0")
+        .unwrap();
+    let addr = server.addrs()[0];
+    let server = server.run();
+    let handle = server.handle();
+    actix_web::rt::spawn(server);
+
+    let mut first = TcpStream::connect(addr).await.unwrap();
+    first.write_all(b"GET / HTTP/1.1\r\nHost: test\r\n\r\n").await.unwrap();
+    let mut buffer = [0u8; 1024];
+    assert!(first.read(&mut buffer).await.unwrap() > 0);
+
+    // La segunda supera el máximo del listener: se cierra sin respuesta
+    let mut second = TcpStream::connect(addr).await.unwrap();
+    second.write_all(b"GET / HTTP/1.1\r\nHost: test\r\n\r\n").await.ok();
+    assert_eq!(second.read(&mut buffer).await.unwrap_or(0), 0);
+    assert_eq!(tracker.snapshot().len(), 1);
+
+    let families = registry.gather();
+    let rejected = families
+        .iter()
+        .find(|family| family.get_name() == "http_connections_rejected_total")
+        .unwrap();
+    let labels: Vec<(&str, &str)> = rejected.get_metric()[0].get_label().iter().map(|l| (l.get_name(), l.get_value())).collect();
+    assert_eq!(labels, [("listener", "test"), ("reason", "max_connections")]);
+    assert_eq!(rejected.get_metric()[0].get_counter().get_value(), 1.0);
+
+    drop(first);
+    handle.stop(true).await;
+}
@@ -0,0 +1,124 @@
+// tests/cors_test.rs
+use actix_web::{middleware, test, web, App, HttpResponse, Responder};
+use servidor::config::Config;
+use servidor::cors::{cors_middleware, CorsPolicies};
+
+async fn ok() -> impl Responder {
+    HttpResponse::Ok().body("ok")
+}
+
+fn policies() -> web::Data<CorsPolicies> {
+    let config = Config::from_toml(
+        r#"
+        [[cors.routes]]
+        path_prefix = "/static/"
+        allowed_origins = ["*"]
+        allowed_methods = ["GET"]
+
+        [[cors.routes]]
+        path_prefix = "/api/"
+        allowed_origins = ["https://app.example.com", "https://*.example.org"]
+        allowed_origin_patterns = ['https://pr-\d+\.preview\.example\.net']
+        supports_credentials = true
+        exposed_headers = ["X-Request-Id"]
+        max_age_secs = 600
+        "#,
+    )
+    .unwrap();
+    web::Data::new(CorsPolicies::new(&config.cors).unwrap())
+}
+
+macro_rules! cors_app {
+    () => {
+        test::init_service(
+            App::new()
+                .app_data(policies())
+                .wrap(middleware::from_fn(cors_middleware))
+                .route("/static/app.js", web::get().to(ok))
+                .route("/api/items", web::get().to(ok))
+                .route("/api/items", web::delete().to(ok))
+                .route("/private", web::get().to(ok)),
+        )
+        .await
+    };
+}
+
+fn get(uri: &str, origin: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri(uri)
+        .insert_header(("Origin", origin.to_string()))
+}
+
+#[actix_web::test]
+async fn test_cors_allowed_origins() {
+    let app = cors_app!();
+
+    for origin in ["https://app.example.com", "https://a.b.example.org", "https://pr-42.preview.example.net"] {
+        let resp = test::call_service(&app, get("/api/items", origin).to_request()).await;
+        assert_eq!(resp.status(), 200, "{}", origin);
+        assert_eq!(resp.headers().get("access-control-allow-origin").unwrap(), origin);
+        assert_eq!(resp.headers().get("access-control-allow-credentials").unwrap(), "true");
+        assert_eq!(resp.headers().get("access-control-expose-headers").unwrap(), "X-Request-Id");
+    }
+
+    let resp = test::call_service(&app, get("/static/app.js", "https://cualquiera.com").to_request()).await;
+    assert_eq!(resp.headers().get("access-control-allow-origin").unwrap(), "*");
+}
+
+#[actix_web::test]
+async fn test_cors_rejects_disallowed_origins() {
+    let app = cors_app!();
+
+    for origin in [
+        "https://evil.com",
+        "https://example.org",
+        "https://app.example.com.evil.com",
+        "http://app.example.com",
+        "https://pr-x.preview.example.net",
+    ] {
+        let resp = test::call_service(&app, get("/api/items", origin).to_request()).await;
+        assert_eq!(resp.status(), 403, "{}", origin);
+        assert!(!resp.headers().contains_key("access-control-allow-origin"));
+    }
+
+    // La política por defecto no admite orígenes externos
+    let resp = test::call_service(&app, get("/private", "https://app.example.com").to_request()).await;
+    assert_eq!(resp.status(), 403);
+
+    // Sin proxies de confianza, X-Forwarded-Host no hace pasar un origen ajeno por el propio
+    let req = get("/private", "https://evil.com")
+        .insert_header(("X-Forwarded-Host", "evil.com"))
+        .insert_header(("X-Forwarded-Proto", "https"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+    // Las reglas se aplican por segmentos de la ruta decodificada
+    let resp = test::call_service(&app, get("/%61pi/items", "https://evil.com").to_request()).await;
+    assert_eq!(resp.status(), 403);
+}
+
+#[actix_web::test]
+async fn test_cors_preflight() {
+    let app = cors_app!();
+
+    let preflight = |method: &str, origin: &str| {
+        test::TestRequest::default()
+            .method(actix_web::http::Method::OPTIONS)
+            .uri("/api/items")
+            .insert_header(("Origin", origin.to_string()))
+            .insert_header(("Access-Control-Request-Method", method.to_string()))
+            .insert_header(("Access-Control-Request-Headers", "content-type"))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, preflight("DELETE", "https://app.example.com")).await;
+    assert_eq!(resp.status(), 204);
+    assert_eq!(resp.headers().get("access-control-max-age").unwrap(), "600");
+    assert_eq!(resp.headers().get("access-control-allow-headers").unwrap(), "content-type");
+
+    let resp = test::call_service(&app, preflight("DELETE", "https://evil.com")).await;
+    assert_eq!(resp.status(), 403);
+
+    let resp = test::call_service(&app, preflight("CONNECT", "https://app.example.com")).await;
+    assert_eq!(resp.status(), 403);
+}
@@ -0,0 +1,78 @@
+// tests/csrf_test.rs
+use actix_session::storage::CookieSessionStore;
+use actix_session::{Session, SessionMiddleware};
+use actix_web::cookie::Key;
+use actix_web::{middleware, test, web, App, HttpResponse, Responder};
+use servidor::config::CsrfConfig;
+use servidor::csrf::{csrf_middleware, csrf_token_handler, Csrf};
+
+async fn update() -> impl Responder {
+    HttpResponse::Ok().body("actualizado")
+}
+
+async fn login(session: Session) -> impl Responder {
+    session.insert("auth_token", "abc").unwrap();
+    HttpResponse::Ok().finish()
+}
+
+#[actix_web::test]
+async fn test_csrf_requires_token_and_origin() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(Csrf::new(CsrfConfig::default())))
+            .wrap(middleware::from_fn(csrf_middleware))
+            .wrap(SessionMiddleware::new(CookieSessionStore::default(), Key::from(&[0; 64])))
+            .route("/csrf-token", web::get().to(csrf_token_handler))
+            .route("/entrar", web::get().to(login))
+            .route("/update", web::post().to(update)),
+    )
+    .await;
+
+    let resp = test::call_service(&app, test::TestRequest::get().uri("/csrf-token").to_request()).await;
+    let cookie = resp.response().cookies().next().unwrap().into_owned();
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    let token = body["token"].as_str().unwrap().to_string();
+
+    let post = || {
+        test::TestRequest::post()
+            .uri("/update")
+            .insert_header(("Host", "localhost:8080"))
+            .cookie(cookie.clone())
+    };
+
+    // Sin token
+    let req = post().insert_header(("Origin", "http://localhost:8080")).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+    // Origen ajeno aunque el token sea válido
+    let req = post()
+        .insert_header(("Origin", "https://evil.example"))
+        .insert_header(("X-CSRF-Token", token.clone()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+    // Token y origen correctos (Referer como alternativa al Origin)
+    let req = post()
+        .insert_header(("Referer", "http://localhost:8080/login"))
+        .insert_header(("X-CSRF-Token", token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    // Solo están exentas las llamadas con el token Bearer de la sesión
+    let resp = test::call_service(&app, test::TestRequest::get().uri("/entrar").to_request()).await;
+    let session = resp.response().cookies().next().unwrap().into_owned();
+    let bearer = |value: &str| {
+        test::TestRequest::post()
+            .uri("/update")
+            .cookie(session.clone())
+            .insert_header(("Authorization", value.to_string()))
+            .to_request()
+    };
+    assert_eq!(test::call_service(&app, bearer("bearer abc")).await.status(), 200);
+    assert_eq!(test::call_service(&app, bearer("bearer junk")).await.status(), 403);
+    let req = test::TestRequest::post()
+        .uri("/update")
+        .insert_header(("Authorization", "Bearer abc"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+}
@@ -0,0 +1,81 @@
+// tests/dos_test.rs
+use actix_web::dev::Payload;
+use actix_web::web::Bytes;
+use actix_web::{middleware, test, web, App, HttpResponse};
+use futures_util::{stream, StreamExt};
+use servidor::config::DosConfig;
+use servidor::dos::{self, DosGuard};
+use std::net::IpAddr;
+use std::time::Instant;
+
+fn guard(config: DosConfig) -> web::Data<DosGuard> {
+    web::Data::new(DosGuard::new(&config, &[], &prometheus::Registry::new()).unwrap())
+}
+
+#[actix_web::test]
+async fn test_strikes_lead_to_temporary_ban() {
+    let guard = guard(DosConfig {
+        ban_after_strikes: 3,
+        ban_secs: 60,
+        exempt: vec!["10.0.0.0/8".parse().unwrap()],
+        ..Default::default()
+    });
+    let app = test::init_service(
+        App::new()
+            .app_data(guard.clone())
+            .wrap(middleware::from_fn(dos::dos_middleware))
+            .default_service(web::to(HttpResponse::Ok)),
+    )
+    .await;
+
+    let ip: IpAddr = "203.0.113.7".parse().unwrap();
+    let request = || test::TestRequest::get().uri("/").peer_addr("203.0.113.7:4000".parse().unwrap()).to_request();
+
+    guard.strike(ip, dos::RATE_LIMIT);
+    guard.strike(ip, dos::SLOW_BODY);
+    assert!(guard.banned_for(ip).is_none());
+    assert_eq!(test::call_service(&app, request()).await.status(), 200);
+
+    guard.strike(ip, dos::RATE_LIMIT);
+    assert!(guard.banned_for(ip).is_some());
+    let res = test::call_service(&app, request()).await;
+    assert_eq!(res.status(), 429);
+    assert!(res.headers().get("retry-after").is_some());
+
+    // Las redes exentas nunca se bloquean
+    let exempt: IpAddr = "10.1.2.3".parse().unwrap();
+    for _ in 0..5 {
+        guard.strike(exempt, dos::RATE_LIMIT);
+    }
+    assert!(guard.banned_for(exempt).is_none());
+}
+
+#[actix_web::test]
+async fn test_stalled_body_gets_408() {
+    let guard = guard(DosConfig {
+        body_timeout_secs: 1,
+        min_body_rate_bytes_per_sec: 0,
+        ..Default::default()
+    });
+    let app = test::init_service(
+        App::new()
+            .app_data(guard)
+            .wrap(middleware::from_fn(dos::dos_middleware))
+            .default_service(web::to(|body: Bytes| async move { HttpResponse::Ok().body(body) })),
+    )
+    .await;
+
+    // Un fragmento y después nada más
+    let body = stream::once(async { Ok(Bytes::from_static(b"hola")) }).chain(stream::pending());
+    let req = test::TestRequest::post().uri("/").to_request();
+    let (req, _) = req.replace_payload(Payload::from(body.boxed_local()));
+
+    let started = Instant::now();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), 408);
+    assert!(started.elapsed().as_secs_f64() < 3.0);
+
+    // Un cuerpo normal no se ve afectado
+    let req = test::TestRequest::post().uri("/").set_payload("hola").to_request();
+    assert_eq!(test::call_and_read_body(&app, req).await, "hola");
+}
@@ -0,0 +1,58 @@
+// tests/error_test.rs
+use actix_web::{middleware, test, web, App, HttpResponse};
+use servidor::config::LoggingConfig;
+use servidor::error::{self, AppError};
+use servidor::handlers;
+use servidor::logging::{self, RequestLogging};
+
+#[actix_web::test]
+async fn test_errors_negotiated_as_problem_details_or_html() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(RequestLogging::new(&LoggingConfig::default())))
+            .wrap(error::error_handlers())
+            .wrap(middleware::from_fn(logging::request_logging_middleware))
+            .route(
+                "/fallo",
+                web::get().to(|| async { Err::<HttpResponse, _>(AppError::Internal("contraseña de la BD".to_string())) }),
+            )
+            .route("/prohibido", web::get().to(|| async { HttpResponse::Forbidden().body("Origen no permitido") }))
+            .default_service(web::route().to(handlers::not_found)),
+    )
+    .await;
+
+    // JSON por defecto, con el ID de petición y la ruta
+    let req = test::TestRequest::get().uri("/no-existe").insert_header(("X-Request-Id", "req-404")).to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), 404);
+    assert_eq!(res.headers().get("content-type").unwrap(), "application/problem+json");
+    let body: serde_json::Value = test::read_body_json(res).await;
+    assert_eq!(body["type"], "about:blank");
+    assert_eq!(body["title"], "Not Found");
+    assert_eq!(body["status"], 404);
+    assert_eq!(body["instance"], "/no-existe");
+    assert_eq!(body["request_id"], "req-404");
+
+    // Los errores internos no muestran el detalle
+    let req = test::TestRequest::get().uri("/fallo").to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["status"], 500);
+    assert!(!body.to_string().contains("contraseña"));
+
+    // Los cuerpos de texto se convierten en el detalle
+    let req = test::TestRequest::get().uri("/prohibido").to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["detail"], "Origen no permitido");
+
+    // Los navegadores reciben la página de static/errors/
+    let req = test::TestRequest::get()
+        .uri("/no-existe")
+        .insert_header(("Accept", "text/html,application/xhtml+xml,*/*;q=0.8"))
+        .insert_header(("X-Request-Id", "req-html"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert!(res.headers().get("content-type").unwrap().to_str().unwrap().starts_with("text/html"));
+    let html = String::from_utf8(test::read_body(res).await.to_vec()).unwrap();
+    assert!(html.contains("404"));
+    assert!(html.contains("req-html"));
+}
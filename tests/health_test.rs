@@ -0,0 +1,67 @@
+// tests/health_test.rs
+use actix_web::{test, web, App};
+use futures_util::future::{self, BoxFuture};
+use servidor::health::{self, DirectoryCheck, Health, HealthCheck, Probe};
+use std::sync::Arc;
+
+struct Failing;
+
+impl HealthCheck for Failing {
+    fn name(&self) -> &str {
+        "base_de_datos"
+    }
+
+    fn check(&self) -> BoxFuture<'_, Result<(), String>> {
+        Box::pin(future::ready(Err("sin conexión".to_string())))
+    }
+}
+
+#[actix_web::test]
+async fn test_readiness_follows_startup_checks_and_shutdown() {
+    let health = web::Data::new(Health::default());
+    health.register(Probe::Readiness, Arc::new(DirectoryCheck::new("static_dir", "./static")));
+    health.register(Probe::Liveness, Arc::new(DirectoryCheck::new("tests_dir", "./tests")));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(health.clone())
+            .route("/healthz", web::get().to(health::healthz))
+            .route("/readyz", web::get().to(health::readyz))
+            .route("/livez", web::get().to(health::livez))
+            .route("/publico/healthz", web::get().to(health::public_healthz)),
+    )
+    .await;
+    let get = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+
+    // Arrancando: vivo pero no listo
+    let res = test::call_service(&app, get("/readyz")).await;
+    assert_eq!(res.status(), 503);
+    let body: serde_json::Value = test::read_body_json(res).await;
+    assert_eq!(body["checks"]["startup"]["status"], "fail");
+    assert_eq!(body["checks"]["static_dir"]["status"], "ok");
+    assert_eq!(test::call_service(&app, get("/livez")).await.status(), 200);
+
+    health.set_started();
+    assert_eq!(test::call_service(&app, get("/readyz")).await.status(), 200);
+
+    // Una comprobación de liveness no afecta a /readyz, pero sí a /healthz
+    health.register(Probe::Liveness, Arc::new(Failing));
+    assert_eq!(test::call_service(&app, get("/readyz")).await.status(), 200);
+    let res = test::call_service(&app, get("/healthz")).await;
+    assert_eq!(res.status(), 503);
+    let body: serde_json::Value = test::read_body_json(res).await;
+    assert_eq!(body["status"], "fail");
+    assert_eq!(body["checks"]["base_de_datos"]["error"], "sin conexión");
+    assert_eq!(body["checks"]["tests_dir"]["status"], "ok");
+
+    // En el listener público, solo el estado
+    let res = test::call_service(&app, get("/publico/healthz")).await;
+    assert_eq!(res.status(), 503);
+    let body: serde_json::Value = test::read_body_json(res).await;
+    assert_eq!(body["checks"]["base_de_datos"], serde_json::json!({ "status": "fail" }));
+
+    // Parada en curso: deja de estar listo
+    health.begin_shutdown();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, get("/readyz")).await;
+    assert_eq!(body["checks"]["shutdown"]["status"], "fail");
+}
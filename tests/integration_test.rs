@@ -18,3 +18,59 @@ async fn test_index_connection() {
     let body = test::read_body(resp).await;
     assert_eq!(body, "Hello, Actix-Web!");
 }
+
+#[actix_web::test]
+async fn test_app_factory_routes() {
+    use servidor::app::{build_app, AppState};
+    use servidor::config::Config;
+    use servidor::metrics::Metrics;
+    use std::sync::Arc;
+
+    let metrics = Arc::new(Metrics::new(Arc::new(prometheus::Registry::new()), &Default::default()));
+    let state = AppState::new(&Config::default(), metrics).unwrap();
+    let app = test::init_service(build_app(&state)).await;
+
+    let req = test::TestRequest::get().uri("/login").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    assert!(resp.headers().contains_key("content-security-policy"));
+
+    // Sin sesión, la página principal redirige al login
+    let req = test::TestRequest::get().uri("/").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.headers().get("location").unwrap(), "/login");
+
+    let req = test::TestRequest::get().uri("/no-existe").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 404);
+
+    let req = test::TestRequest::get().uri("/static/login/login_styles.css").to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+
+    // Nada fuera de ./static, ni con ".." literal ni codificado
+    for uri in ["/static/%2e%2e/config.toml", "/static/../config.toml", "/static/login/../../Cargo.toml"] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404, "{}", uri);
+    }
+}
+
+#[actix_web::test]
+async fn test_session_key_from_config() {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use servidor::app::AppState;
+    use servidor::config::Config;
+    use servidor::metrics::Metrics;
+    use std::sync::Arc;
+
+    let state_with_key = |key: &str| {
+        let mut config = Config::default();
+        config.login.session_key = Some(key.to_string());
+        let metrics = Arc::new(Metrics::new(Arc::new(prometheus::Registry::new()), &Default::default()));
+        AppState::new(&config, metrics)
+    };
+
+    assert!(state_with_key(&STANDARD.encode([7u8; 64])).is_ok());
+    // Una clave corta o que no es base64 impide arrancar
+    assert!(state_with_key(&STANDARD.encode([7u8; 16])).is_err());
+    assert!(state_with_key("no es base64").is_err());
+}
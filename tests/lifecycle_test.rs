@@ -0,0 +1,67 @@
+// tests/lifecycle_test.rs
+use servidor::lifecycle::{self, BackgroundTasks};
+use servidor::server;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[actix_web::test]
+async fn test_background_tasks_stop_on_shutdown() {
+    let stopped = Arc::new(AtomicBool::new(false));
+
+    // Se marca al soltar la tarea, es decir, al cancelarla
+    struct OnDrop(Arc<AtomicBool>);
+    impl Drop for OnDrop {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    let mut tasks = BackgroundTasks::default();
+    let guard = OnDrop(stopped.clone());
+    tasks.spawn("infinita", async move {
+        let _guard = guard;
+        std::future::pending::<()>().await;
+    });
+
+    tasks.shutdown(Duration::from_secs(1)).await;
+    assert!(stopped.load(Ordering::SeqCst));
+}
+
+#[cfg(unix)]
+#[actix_web::test]
+async fn test_reuse_port_allows_second_listener() {
+    let first = server::reuse_port_listener("127.0.0.1:0").unwrap();
+    let addr = first.local_addr().unwrap().to_string();
+
+    // Un segundo proceso (aquí, un segundo socket) puede escuchar en el mismo puerto
+    let second = server::reuse_port_listener(&addr).unwrap();
+    assert_eq!(second.local_addr().unwrap().to_string(), addr);
+}
+
+#[test]
+fn test_reload_reports_sections_that_need_restart() {
+    let running: toml::Table = r#"
+        [rate_limit]
+        requests_per_second = 10
+        [redirects]
+        rules = []
+        [tls]
+        certificates = []
+    "#
+    .parse()
+    .unwrap();
+    let loaded: toml::Table = r#"
+        [rate_limit]
+        requests_per_second = 20
+        [tls]
+        certificates = [{ cert_path = "a.crt", key_path = "a.key" }]
+        [cors]
+        allowed_origins = ["https://example.com"]
+    "#
+    .parse()
+    .unwrap();
+
+    assert_eq!(lifecycle::restart_required(&running, &loaded), ["cors", "rate_limit"]);
+    assert!(lifecycle::restart_required(&running, &running).is_empty());
+}
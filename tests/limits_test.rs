@@ -0,0 +1,81 @@
+// tests/limits_test.rs
+use actix_web::{middleware, test, web, App, HttpResponse};
+use servidor::config::{LimitsConfig, RouteBodyLimit};
+use servidor::limits::{self, RequestLimits};
+
+fn config() -> LimitsConfig {
+    LimitsConfig {
+        max_uri_length: 64,
+        max_header_bytes: 256,
+        max_header_count: 8,
+        max_body_bytes: 16,
+        routes: vec![RouteBodyLimit {
+            path_prefix: "/subida".to_string(),
+            content_type: Some("application/octet-stream".to_string()),
+            max_body_bytes: 1024,
+        }],
+        ..Default::default()
+    }
+}
+
+#[actix_web::test]
+async fn test_uri_header_and_body_limits() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(RequestLimits::new(&config())))
+            .wrap(middleware::from_fn(limits::limits_middleware))
+            .default_service(web::to(|body: web::Bytes| async move { HttpResponse::Ok().body(body) })),
+    )
+    .await;
+
+    // URL: justo en el límite y un byte por encima
+    for (length, status) in [(64, 200), (65, 414), (4096, 414)] {
+        let uri = format!("/{}", "a".repeat(length - 1));
+        let res = test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+        assert_eq!(res.status(), status, "URL de {} bytes", length);
+    }
+
+    // Cabeceras: demasiadas o demasiado grandes
+    let mut req = test::TestRequest::get().uri("/");
+    for i in 0..9 {
+        req = req.insert_header((format!("x-extra-{}", i), "1"));
+    }
+    assert_eq!(test::call_service(&app, req.to_request()).await.status(), 431);
+    let req = test::TestRequest::get().uri("/").insert_header(("x-grande", "a".repeat(300))).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 431);
+
+    // Cuerpo: límite general y límite por ruta y tipo de contenido
+    let post = |uri: &str, size: usize, content_type: &str| {
+        test::TestRequest::post()
+            .uri(uri)
+            .insert_header(("content-type", content_type))
+            .insert_header(("content-length", size.to_string()))
+            .set_payload(vec![b'x'; size])
+            .to_request()
+    };
+    assert_eq!(test::call_service(&app, post("/", 16, "text/plain")).await.status(), 200);
+    let res = test::call_service(&app, post("/", 17, "text/plain")).await;
+    assert_eq!(res.status(), 413);
+    assert_eq!(res.headers().get("content-type").unwrap(), "application/problem+json");
+    assert_eq!(test::call_service(&app, post("/subida", 1024, "application/octet-stream")).await.status(), 200);
+    assert_eq!(test::call_service(&app, post("/subida", 1025, "application/octet-stream")).await.status(), 413);
+    assert_eq!(test::call_service(&app, post("/subida", 17, "text/plain")).await.status(), 413);
+    // El prefijo se compara por segmentos sobre la ruta decodificada
+    assert_eq!(test::call_service(&app, post("/%73ubida", 1024, "application/octet-stream")).await.status(), 200);
+    assert_eq!(test::call_service(&app, post("/subidas", 1024, "application/octet-stream")).await.status(), 413);
+}
+
+#[actix_web::test]
+async fn test_body_without_content_length_is_limited_while_read() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(RequestLimits::new(&config())))
+            .wrap(middleware::from_fn(limits::limits_middleware))
+            .default_service(web::to(|body: web::Bytes| async move { HttpResponse::Ok().body(body) })),
+    )
+    .await;
+
+    // TestRequest no añade Content-Length: el cuerpo llega como si fuera chunked
+    let req = test::TestRequest::post().uri("/").set_payload(vec![b'x'; 32]).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 413);
+}
@@ -0,0 +1,36 @@
+// tests/logging_test.rs
+use actix_web::{middleware, test, web, App, HttpResponse};
+use servidor::config::LoggingConfig;
+use servidor::logging::{self, RequestId, RequestLogging};
+
+#[actix_web::test]
+async fn test_request_id_propagated_and_echoed_in_errors() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(RequestLogging::new(&LoggingConfig::default())))
+            .wrap(middleware::from_fn(logging::request_logging_middleware))
+            .route("/id", web::get().to(|id: RequestId| async move { HttpResponse::Ok().body(id.0) }))
+            .route(
+                "/error",
+                web::get().to(|| async { HttpResponse::BadRequest().json(serde_json::json!({"error": "Bad Request"})) }),
+            ),
+    )
+    .await;
+
+    // Se reutiliza el ID recibido y se devuelve en la cabecera
+    let req = test::TestRequest::get().uri("/id").insert_header(("X-Request-Id", "abc-123")).to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.headers().get("x-request-id").unwrap(), "abc-123");
+    assert_eq!(test::read_body(res).await, "abc-123");
+
+    // Un ID inválido se sustituye por uno generado
+    let req = test::TestRequest::get().uri("/id").insert_header(("X-Request-Id", "a b\"c")).to_request();
+    let res = test::call_service(&app, req).await;
+    let generated = res.headers().get("x-request-id").unwrap().to_str().unwrap().to_string();
+    assert_eq!(generated.len(), 36);
+
+    let req = test::TestRequest::get().uri("/error").insert_header(("X-Request-Id", "err-1")).to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["request_id"], "err-1");
+    assert_eq!(body["error"], "Bad Request");
+}
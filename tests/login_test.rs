@@ -0,0 +1,75 @@
+// tests/login_test.rs
+use actix_session::storage::CookieSessionStore;
+use actix_session::SessionMiddleware;
+use actix_web::cookie::Key;
+use actix_web::{test, web, App};
+use argon2::Params;
+use servidor::config::{LoginConfig, UserCredentials};
+use servidor::login::{hash_password_with, login_handler, LoginGuard, UserStore};
+
+fn login_config() -> LoginConfig {
+    // Parámetros Argon2 mínimos para que las pruebas sean rápidas
+    let params = Params::new(8, 1, 1, None).unwrap();
+    LoginConfig {
+        max_failures_per_user: 3,
+        max_failures_per_ip: 100,
+        min_response_ms: 0,
+        users: vec![UserCredentials {
+            username: "admin".to_string(),
+            password_hash: hash_password_with("secreto", params).unwrap(),
+        }],
+        ..LoginConfig::default()
+    }
+}
+
+fn login_request(username: &str, password: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri("/login")
+        .peer_addr("198.51.100.1:5000".parse().unwrap())
+        .set_json(serde_json::json!({ "username": username, "password": password }))
+}
+
+macro_rules! login_app {
+    ($config:expr) => {{
+        let config = $config;
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new(UserStore::new(&config.users)))
+                .app_data(web::Data::new(LoginGuard::new(config)))
+                .wrap(SessionMiddleware::new(CookieSessionStore::default(), Key::from(&[0; 64])))
+                .route("/login", web::post().to(login_handler)),
+        )
+        .await
+    }};
+}
+
+#[actix_web::test]
+async fn test_login_unknown_user_looks_like_wrong_password() {
+    let app = login_app!(login_config());
+
+    let resp = test::call_service(&app, login_request("admin", "incorrecta").to_request()).await;
+    assert_eq!(resp.status(), 401);
+    let wrong_password = test::read_body(resp).await;
+
+    let resp = test::call_service(&app, login_request("nadie", "incorrecta").to_request()).await;
+    assert_eq!(resp.status(), 401);
+    assert_eq!(test::read_body(resp).await, wrong_password);
+
+    let resp = test::call_service(&app, login_request("admin", "secreto").to_request()).await;
+    assert_eq!(resp.status(), 200);
+}
+
+#[actix_web::test]
+async fn test_login_locks_user_after_repeated_failures() {
+    let app = login_app!(login_config());
+
+    for _ in 0..3 {
+        let resp = test::call_service(&app, login_request("admin", "incorrecta").to_request()).await;
+        assert_eq!(resp.status(), 401);
+    }
+
+    // Bloqueado aunque la contraseña sea correcta
+    let resp = test::call_service(&app, login_request("admin", "secreto").to_request()).await;
+    assert_eq!(resp.status(), 429);
+    assert!(resp.headers().contains_key("retry-after"));
+}
@@ -0,0 +1,39 @@
+// tests/metrics_test.rs
+use actix_web::{middleware, test, web, App, HttpResponse};
+use servidor::config::MetricsConfig;
+use servidor::metrics::{self, Metrics};
+use std::sync::Arc;
+
+#[actix_web::test]
+async fn test_requests_labeled_by_route_pattern() {
+    let config = MetricsConfig {
+        max_routes: 1,
+        ..Default::default()
+    };
+    let metrics = web::Data::new(Metrics::new(Arc::new(prometheus::Registry::new()), &config));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(metrics.clone())
+            .wrap(middleware::from_fn(metrics::metrics_middleware))
+            .route("/items/{id}", web::get().to(|| async { HttpResponse::Ok().body("item") }))
+            .route("/otra", web::get().to(HttpResponse::Ok))
+            .default_service(web::to(HttpResponse::NotFound)),
+    )
+    .await;
+
+    for uri in ["/items/1", "/items/2", "/otra", "/no-existe"] {
+        test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+    }
+
+    let count = |route: &str, status: &str| {
+        metrics.http_requests_total.with_label_values(&["GET", route, status]).get()
+    };
+    // Las rutas reales se agrupan por patrón
+    assert_eq!(count("/items/{id}", "2xx"), 2);
+    // Con max_routes = 1, las rutas nuevas van a "other"
+    assert_eq!(count("other", "2xx"), 1);
+    assert_eq!(count("unmatched", "4xx"), 1);
+    assert_eq!(metrics.in_flight.get(), 0);
+    assert_eq!(metrics.response_size.with_label_values(&["GET", "/items/{id}"]).get_sample_count(), 2);
+}
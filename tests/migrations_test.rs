@@ -0,0 +1,76 @@
+// tests/migrations_test.rs
+use servidor::config::DatabaseConfig;
+use servidor::db::Database;
+use servidor::migrations::{self, MigrationState};
+use std::fs;
+
+fn states(status: &[migrations::MigrationStatus]) -> Vec<(i64, MigrationState)> {
+    status.iter().map(|migration| (migration.version, migration.state)).collect()
+}
+
+#[actix_web::test]
+async fn test_embedded_migrations_apply_and_verify() {
+    let config = DatabaseConfig {
+        url: "sqlite::memory:".to_string(),
+        ..DatabaseConfig::default()
+    };
+    let database = Database::connect(&config).unwrap();
+    assert!(migrations::verify(&database).await.is_err());
+    assert!(!migrations::up(&database).await.unwrap().is_empty());
+    migrations::verify(&database).await.unwrap();
+    assert!(migrations::up(&database).await.unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn test_migrations_up_down_status_and_checksums() {
+    let dir = std::env::temp_dir().join(format!("migrations_test_{}", std::process::id()));
+    let sqlite = dir.join("sqlite");
+    fs::create_dir_all(&sqlite).unwrap();
+    fs::write(sqlite.join("1_crear_a.up.sql"), "CREATE TABLE a (id INTEGER);").unwrap();
+    fs::write(sqlite.join("1_crear_a.down.sql"), "DROP TABLE a;").unwrap();
+    fs::write(sqlite.join("2_crear_b.up.sql"), "CREATE TABLE b (id INTEGER);").unwrap();
+    fs::write(sqlite.join("2_crear_b.down.sql"), "DROP TABLE b;").unwrap();
+
+    let config = DatabaseConfig {
+        url: "sqlite::memory:".to_string(),
+        migrations_dir: Some(dir.to_string_lossy().into_owned()),
+        ..DatabaseConfig::default()
+    };
+    let database = Database::connect(&config).unwrap();
+
+    let status = migrations::status(&database).await.unwrap();
+    assert_eq!(states(&status), [(1, MigrationState::Pending), (2, MigrationState::Pending)]);
+    assert_eq!(status[1].description, "crear b");
+    assert_eq!(migrations::up(&database).await.unwrap(), [1, 2]);
+    migrations::verify(&database).await.unwrap();
+
+    // Sin versión se revierte solo la última
+    assert_eq!(migrations::down(&database, None).await.unwrap(), [2]);
+    let status = migrations::status(&database).await.unwrap();
+    assert_eq!(states(&status), [(1, MigrationState::Applied), (2, MigrationState::Pending)]);
+    assert!(migrations::verify(&database).await.is_err());
+    migrations::up(&database).await.unwrap();
+
+    // Una migración aplicada que cambia impide aplicar y arrancar
+    fs::write(sqlite.join("1_crear_a.up.sql"), "CREATE TABLE a (id INTEGER, nombre TEXT);").unwrap();
+    let status = migrations::status(&database).await.unwrap();
+    assert_eq!(status[0].state, MigrationState::Modified);
+    assert!(migrations::verify(&database).await.unwrap_err().to_string().contains("1 (modificada)"));
+    assert!(migrations::up(&database).await.unwrap_err().to_string().contains("checksum"));
+    fs::write(sqlite.join("1_crear_a.up.sql"), "CREATE TABLE a (id INTEGER);").unwrap();
+
+    // Sin .down.sql no se revierte nada
+    fs::remove_file(sqlite.join("2_crear_b.down.sql")).unwrap();
+    assert!(migrations::down(&database, Some(0)).await.is_err());
+    assert_eq!(states(&migrations::status(&database).await.unwrap()), [(1, MigrationState::Applied), (2, MigrationState::Applied)]);
+
+    // `migrate new` crea la pareja up/down para cada motor
+    let created = migrations::new_migration(&dir, "Crear tabla de usuarios").unwrap();
+    assert_eq!(created.len(), 4);
+    let name = created[0].file_name().unwrap().to_string_lossy().into_owned();
+    assert!(name.ends_with("_crear_tabla_de_usuarios.up.sql"), "{}", name);
+    assert!(dir.join("postgres").join(name.replace(".up.", ".down.")).exists());
+    assert!(migrations::new_migration(&dir, " -- ").is_err());
+
+    fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,65 @@
+// tests/mtls_test.rs
+use actix_web::{middleware, test, web, App, HttpResponse};
+use servidor::config::Config;
+use servidor::mtls::{self, MtlsAuthorizer, PeerCertificate};
+
+fn mtls_config() -> Config {
+    Config::from_toml(
+        r#"
+        [[mtls.identities]]
+        common_name = "monitorizacion"
+        roles = ["metrics"]
+
+        [[mtls.identities]]
+        san = "spiffe://interno/pagos"
+        roles = ["admin"]
+
+        [[mtls.routes]]
+        path_prefix = "/admin"
+        roles = ["admin"]
+        "#,
+    )
+    .unwrap()
+}
+
+#[actix_web::test]
+async fn test_peer_certificate_roles() {
+    let mut params = rcgen::CertificateParams::new(vec!["pagos.interno".to_string()]).unwrap();
+    params.distinguished_name.push(rcgen::DnType::CommonName, "monitorizacion");
+    params
+        .subject_alt_names
+        .push(rcgen::SanType::URI("spiffe://interno/pagos".try_into().unwrap()));
+    let key = rcgen::KeyPair::generate().unwrap();
+    let cert = params.self_signed(&key).unwrap();
+
+    let peer = PeerCertificate::from_der(cert.der()).unwrap();
+    assert_eq!(peer.common_name.as_deref(), Some("monitorizacion"));
+    assert!(peer.alt_names.contains(&"pagos.interno".to_string()));
+
+    let authorizer = MtlsAuthorizer::new(&mtls_config().mtls);
+    assert_eq!(authorizer.roles_for(&peer), vec!["admin", "metrics"]);
+    assert!(authorizer.roles_for(&PeerCertificate::default()).is_empty());
+}
+
+#[actix_web::test]
+async fn test_protected_route_requires_certificate() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(MtlsAuthorizer::new(&mtls_config().mtls)))
+            .wrap(middleware::from_fn(mtls::mtls_middleware))
+            .route("/admin/estado", web::get().to(HttpResponse::Ok))
+            .route("/publico", web::get().to(HttpResponse::Ok)),
+    )
+    .await;
+
+    // Sin conexión TLS no hay certificado de cliente
+    let res = test::call_service(&app, test::TestRequest::get().uri("/admin/estado").to_request()).await;
+    assert_eq!(res.status(), 403);
+
+    // Codificar la ruta no evita la regla
+    let res = test::call_service(&app, test::TestRequest::get().uri("/%61dmin/estado").to_request()).await;
+    assert_eq!(res.status(), 403);
+
+    let res = test::call_service(&app, test::TestRequest::get().uri("/publico").to_request()).await;
+    assert_eq!(res.status(), 200);
+}
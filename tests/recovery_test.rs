@@ -0,0 +1,41 @@
+// tests/recovery_test.rs
+use actix_web::{body, middleware, test, web, App, HttpResponse};
+use servidor::config::MetricsConfig;
+use servidor::metrics::Metrics;
+use servidor::recovery;
+use std::sync::Arc;
+
+#[actix_web::test]
+async fn test_handler_panic_becomes_500() {
+    let metrics = web::Data::new(Metrics::new(Arc::new(prometheus::Registry::new()), &MetricsConfig::default()));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(metrics.clone())
+            .wrap(middleware::from_fn(recovery::panic_recovery_middleware))
+            .route("/panico", web::get().to(|| async { panic!("secreto interno") as HttpResponse }))
+            .route("/bien", web::get().to(HttpResponse::Ok)),
+    )
+    .await;
+
+    // JSON por defecto, sin el mensaje del pánico
+    let req = test::TestRequest::get().uri("/panico").to_request();
+    let res = test::try_call_service(&app, req).await.unwrap_err().error_response();
+    assert_eq!(res.status(), 500);
+    assert_eq!(res.headers().get("content-type").unwrap(), "application/problem+json");
+    let bytes = body::to_bytes(res.into_body()).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(body["status"], 500);
+    assert_eq!(body["instance"], "/panico");
+    assert!(!body.to_string().contains("secreto"));
+
+    // Página HTML para los navegadores
+    let req = test::TestRequest::get().uri("/panico").insert_header(("Accept", "text/html")).to_request();
+    let res = test::try_call_service(&app, req).await.unwrap_err().error_response();
+    assert!(res.headers().get("content-type").unwrap().to_str().unwrap().starts_with("text/html"));
+
+    // El servicio sigue atendiendo peticiones
+    let req = test::TestRequest::get().uri("/bien").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    assert_eq!(metrics.panics.get(), 2);
+}
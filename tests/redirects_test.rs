@@ -0,0 +1,123 @@
+// tests/redirects_test.rs
+use actix_web::dev::ServiceResponse;
+use actix_web::{middleware, test, web, App, HttpRequest, HttpResponse};
+use servidor::config::{RedirectMap, RedirectRule, RedirectsConfig};
+use servidor::redirects::{self, Redirects};
+use std::fs;
+
+fn rule(pattern: &str, target: &str, status: u16) -> RedirectRule {
+    RedirectRule {
+        host: None,
+        pattern: pattern.to_string(),
+        target: target.to_string(),
+        status,
+        rewrite: false,
+        preserve_query: true,
+    }
+}
+
+fn location<B>(res: &ServiceResponse<B>) -> String {
+    res.headers().get("location").unwrap().to_str().unwrap().to_string()
+}
+
+#[actix_web::test]
+async fn test_redirects_rewrites_and_maps() {
+    let dir = std::env::temp_dir().join(format!("redirects_test_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let map_path = dir.join("mapa.csv");
+    fs::write(&map_path, "# origen,destino[,estado]\n/viejo,/nuevo\n/temporal,/otro,307\n").unwrap();
+    let rules_path = dir.join("reglas.toml");
+    fs::write(&rules_path, "[[rules]]\npattern = \"/archivo\"\ntarget = \"/archivo-v1\"\n").unwrap();
+
+    let config = RedirectsConfig {
+        rules_file: Some(rules_path.to_string_lossy().into_owned()),
+        rules: vec![
+            rule("/blog/(?<anio>[0-9]{4})/(.*)", "/articulos/${anio}/$2", 308),
+            RedirectRule {
+                host: Some("*.example.com".to_string()),
+                ..rule("/.*", "https://example.com/", 302)
+            },
+            RedirectRule {
+                rewrite: true,
+                ..rule("/docs/(.*)", "/static/docs/$1", 0)
+            },
+            RedirectRule {
+                preserve_query: false,
+                ..rule("/buscar", "/search?q=todo", 301)
+            },
+        ],
+        maps: vec![RedirectMap {
+            file: map_path.to_string_lossy().into_owned(),
+            status: 301,
+            preserve_query: true,
+        }],
+    };
+    let redirects = web::Data::new(Redirects::new(&config).unwrap());
+
+    let app = test::init_service(
+        App::new()
+            .app_data(redirects.clone())
+            .wrap(middleware::from_fn(redirects::redirects_middleware))
+            .route(
+                "/static/docs/{page}",
+                web::get().to(|req: HttpRequest| async move {
+                    HttpResponse::Ok().body(format!("{}?{}", req.match_info().query("page"), req.query_string()))
+                }),
+            )
+            .default_service(web::to(HttpResponse::NotFound)),
+    )
+    .await;
+    let get = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+
+    // Capturas con nombre y por posición; la query se conserva
+    let res = test::call_service(&app, get("/blog/2024/hola?ref=x")).await;
+    assert_eq!(res.status(), 308);
+    assert_eq!(location(&res), "/articulos/2024/hola?ref=x");
+
+    // Mapas CSV, con el estado por defecto o el de la línea
+    let res = test::call_service(&app, get("/viejo")).await;
+    assert_eq!(res.status(), 301);
+    assert_eq!(location(&res), "/nuevo");
+    assert_eq!(test::call_service(&app, get("/temporal")).await.status(), 307);
+
+    // Sin conservar la query
+    let res = test::call_service(&app, get("/buscar?q=rust")).await;
+    assert_eq!(location(&res), "/search?q=todo");
+
+    // Reglas por host
+    let req = test::TestRequest::get().uri("/x").insert_header(("Host", "www.example.com:8080")).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 302);
+    let req = test::TestRequest::get().uri("/x").insert_header(("Host", "example.com")).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 404);
+    // Sin proxy de confianza, X-Forwarded-Host no cambia el host
+    let req = test::TestRequest::get()
+        .uri("/x")
+        .insert_header(("Host", "example.com"))
+        .insert_header(("X-Forwarded-Host", "www.example.com"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 404);
+
+    // Reescritura interna: el router ve la ruta nueva
+    let body = test::call_and_read_body(&app, get("/docs/intro?v=2")).await;
+    assert_eq!(body, "intro?v=2");
+
+    // Recarga del archivo de reglas; si no es válido se mantienen las anteriores
+    assert_eq!(location(&test::call_service(&app, get("/archivo")).await), "/archivo-v1");
+    fs::write(&rules_path, "[[rules]]\npattern = \"/archivo\"\ntarget = \"/archivo-v2\"\n").unwrap();
+    redirects.reload().unwrap();
+    assert_eq!(location(&test::call_service(&app, get("/archivo")).await), "/archivo-v2");
+    fs::write(&rules_path, "[[rules]]\npattern = \"(\"\ntarget = \"/x\"\n").unwrap();
+    assert!(redirects.reload().is_err());
+    assert_eq!(location(&test::call_service(&app, get("/archivo")).await), "/archivo-v2");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[actix_web::test]
+async fn test_invalid_status_is_rejected() {
+    let config = RedirectsConfig {
+        rules: vec![rule("/a", "/b", 200)],
+        ..Default::default()
+    };
+    assert!(Redirects::new(&config).is_err());
+}
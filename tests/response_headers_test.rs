@@ -0,0 +1,76 @@
+// tests/response_headers_test.rs
+use actix_web::{middleware, test, web, App, HttpResponse};
+use servidor::config::{LoggingConfig, ResponseHeaderRule, ResponseHeadersConfig};
+use servidor::logging::{self, RequestLogging};
+use servidor::response_headers::{self, ResponseHeaders};
+use std::collections::BTreeMap;
+
+fn map(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+    entries.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+}
+
+#[actix_web::test]
+async fn test_rules_by_path_and_method() {
+    let config = ResponseHeadersConfig {
+        rules: vec![
+            ResponseHeaderRule {
+                remove: vec!["Server".to_string()],
+                ..Default::default()
+            },
+            ResponseHeaderRule {
+                path: Some("/static/**".to_string()),
+                methods: vec!["GET".to_string()],
+                set: map(&[("Cache-Control", "public, max-age=60")]),
+                ..Default::default()
+            },
+            ResponseHeaderRule {
+                path_pattern: Some("/api/v[0-9]+/.*".to_string()),
+                add: map(&[("X-Trace", "{method} {path} {request_id}")]),
+                ..Default::default()
+            },
+        ],
+    };
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(ResponseHeaders::new(&config).unwrap()))
+            .app_data(web::Data::new(RequestLogging::new(&LoggingConfig::default())))
+            .wrap(middleware::from_fn(response_headers::response_headers_middleware))
+            .wrap(middleware::from_fn(logging::request_logging_middleware))
+            .default_service(web::to(|| async {
+                HttpResponse::Ok()
+                    .insert_header(("Server", "actix"))
+                    .insert_header(("Cache-Control", "no-cache"))
+                    .finish()
+            })),
+    )
+    .await;
+
+    // "**" cruza directorios; la regla general quita Server
+    let res = test::call_service(&app, test::TestRequest::get().uri("/static/css/a.css").to_request()).await;
+    assert_eq!(res.headers().get("cache-control").unwrap(), "public, max-age=60");
+    assert!(res.headers().get("server").is_none());
+
+    // Otro método u otra ruta: sin cambios
+    let res = test::call_service(&app, test::TestRequest::post().uri("/static/a.css").to_request()).await;
+    assert_eq!(res.headers().get("cache-control").unwrap(), "no-cache");
+    let res = test::call_service(&app, test::TestRequest::get().uri("/otra").to_request()).await;
+    assert_eq!(res.headers().get("cache-control").unwrap(), "no-cache");
+
+    // Valores con plantilla
+    let req = test::TestRequest::delete().uri("/api/v2/items").insert_header(("X-Request-Id", "abc")).to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.headers().get("x-trace").unwrap(), "DELETE /api/v2/items abc");
+    let res = test::call_service(&app, test::TestRequest::get().uri("/api/items").to_request()).await;
+    assert!(res.headers().get("x-trace").is_none());
+}
+
+#[actix_web::test]
+async fn test_invalid_rules_are_rejected() {
+    let config = ResponseHeadersConfig {
+        rules: vec![ResponseHeaderRule {
+            set: map(&[("Cabecera inválida", "x")]),
+            ..Default::default()
+        }],
+    };
+    assert!(ResponseHeaders::new(&config).is_err());
+}
@@ -0,0 +1,54 @@
+// tests/security_headers_test.rs
+use actix_web::{middleware, test, web, App, HttpResponse, Responder};
+use servidor::config::Config;
+use servidor::security_headers::{security_headers_middleware, SecurityHeaders};
+
+async fn html_page() -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(r#"<script nonce="{{csp_nonce}}">console.log(1)</script>"#)
+}
+
+#[actix_web::test]
+async fn test_security_headers_and_nonce_injection() {
+    let config = Config::from_toml(
+        r#"
+        [[security_headers.routes]]
+        path_prefix = "/embed"
+        x_frame_options = "SAMEORIGIN"
+        content_security_policy = ""
+        "#,
+    )
+    .unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(SecurityHeaders::new(&config.security_headers)))
+            .wrap(middleware::from_fn(security_headers_middleware))
+            .route("/", web::get().to(html_page))
+            .route("/embed", web::get().to(html_page))
+            .route("/embedded", web::get().to(html_page)),
+    )
+    .await;
+
+    let resp = test::call_service(&app, test::TestRequest::get().uri("/").to_request()).await;
+    assert_eq!(resp.headers().get("x-frame-options").unwrap(), "DENY");
+    assert_eq!(resp.headers().get("x-content-type-options").unwrap(), "nosniff");
+    assert!(resp.headers().contains_key("strict-transport-security"));
+
+    let csp = resp.headers().get("content-security-policy").unwrap().to_str().unwrap().to_string();
+    let nonce = csp.split("'nonce-").nth(1).unwrap().split('\'').next().unwrap().to_string();
+    let body = test::read_body(resp).await;
+    assert_eq!(body, format!(r#"<script nonce="{}">console.log(1)</script>"#, nonce));
+
+    // Ajustes por ruta
+    let resp = test::call_service(&app, test::TestRequest::get().uri("/embed").to_request()).await;
+    assert_eq!(resp.headers().get("x-frame-options").unwrap(), "SAMEORIGIN");
+    assert!(!resp.headers().contains_key("content-security-policy"));
+
+    // El prefijo se compara por segmentos completos y con la ruta decodificada
+    let resp = test::call_service(&app, test::TestRequest::get().uri("/embedded").to_request()).await;
+    assert_eq!(resp.headers().get("x-frame-options").unwrap(), "DENY");
+    let resp = test::call_service(&app, test::TestRequest::get().uri("/%65mbed").to_request()).await;
+    assert_eq!(resp.headers().get("x-frame-options").unwrap(), "SAMEORIGIN");
+}
@@ -0,0 +1,96 @@
+// tests/telemetry_test.rs
+use actix_web::{middleware, test, web, App, HttpResponse};
+use servidor::config::{LoggingConfig, TracingConfig};
+use servidor::logging::{self, RequestLogging};
+use servidor::telemetry::Telemetry;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+
+const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+const PARENT_SPAN_ID: &str = "00f067aa0ba902b7";
+
+// Colector OTLP mínimo: guarda el cuerpo de cada POST y responde 200
+fn stub_collector() -> (String, Arc<Mutex<Vec<Vec<u8>>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}/v1/traces", listener.local_addr().unwrap());
+    let received = Arc::new(Mutex::new(Vec::new()));
+
+    let bodies = received.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            bodies.lock().unwrap().push(body);
+            stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").unwrap();
+        }
+    });
+
+    (endpoint, received)
+}
+
+fn hex(value: &str) -> Vec<u8> {
+    (0..value.len()).step_by(2).map(|i| u8::from_str_radix(&value[i..i + 2], 16).unwrap()).collect()
+}
+
+#[actix_web::test]
+async fn test_traceparent_continued_and_exported() {
+    let (endpoint, received) = stub_collector();
+    let config = TracingConfig {
+        enabled: true,
+        endpoint,
+        // Las trazas nuevas no se muestrean, pero sí las que el cliente ya muestreó
+        sample_ratio: 0.0,
+        ..TracingConfig::default()
+    };
+    let telemetry = Telemetry::new(&config).unwrap();
+    logging::init(&LoggingConfig::default(), &telemetry).unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(RequestLogging::new(&LoggingConfig::default())))
+            .wrap(middleware::from_fn(logging::request_logging_middleware))
+            .route("/items/{id}", web::get().to(|| async { HttpResponse::Ok().finish() })),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/items/7")
+        .insert_header(("traceparent", format!("00-{}-{}-01", TRACE_ID, PARENT_SPAN_ID)))
+        .insert_header(("tracestate", "proveedor=valor"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+
+    // La respuesta continúa la traza con un span propio
+    let traceparent = res.headers().get("traceparent").unwrap().to_str().unwrap().to_string();
+    let parts: Vec<&str> = traceparent.split('-').collect();
+    assert_eq!(parts[1], TRACE_ID);
+    assert_ne!(parts[2], PARENT_SPAN_ID);
+    assert_eq!(parts[3], "01");
+    assert_eq!(res.headers().get("tracestate").unwrap(), "proveedor=valor");
+
+    tokio::task::spawn_blocking(move || telemetry.shutdown()).await.unwrap();
+
+    let bodies = received.lock().unwrap();
+    let exported = bodies.concat();
+    let contains = |needle: &[u8]| exported.windows(needle.len()).any(|window| window == needle);
+    assert!(contains(&hex(TRACE_ID)));
+    assert!(contains(&hex(PARENT_SPAN_ID)));
+    assert!(contains(b"GET /items/{id}"));
+    assert!(contains(b"servidor"));
+}
@@ -0,0 +1,92 @@
+// tests/tls_test.rs
+use actix_web::{test, web, App};
+use servidor::config::{CertificateConfig, ListenerConfig, ListenerKind, TlsConfig};
+use servidor::tls::{self, CertStore, HttpsRedirect};
+use std::fs;
+use std::sync::Arc;
+
+fn write_self_signed(dir: &std::path::Path, name: &str) -> CertificateConfig {
+    let cert = rcgen::generate_simple_self_signed(vec![name.to_string()]).unwrap();
+    let cert_path = dir.join(format!("{}.crt", name));
+    let key_path = dir.join(format!("{}.key", name));
+    fs::write(&cert_path, cert.cert.pem()).unwrap();
+    fs::write(&key_path, cert.key_pair.serialize_pem()).unwrap();
+
+    CertificateConfig {
+        cert_path: cert_path.to_string_lossy().into_owned(),
+        key_path: key_path.to_string_lossy().into_owned(),
+        server_names: vec![name.to_string()],
+    }
+}
+
+#[actix_web::test]
+async fn test_cert_store_load_and_reload() {
+    let dir = std::env::temp_dir().join(format!("tls_test_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let sources = vec![write_self_signed(&dir, "example.com"), write_self_signed(&dir, "api.example.com")];
+    let store = CertStore::load(&sources).unwrap();
+    assert!(tls::server_config(Arc::new(store), None).is_ok());
+
+    // Un archivo dañado no invalida los certificados ya cargados
+    let store = CertStore::load(&sources).unwrap();
+    fs::write(&sources[0].key_path, "no es una clave").unwrap();
+    assert!(store.reload().is_err());
+
+    write_self_signed(&dir, "example.com");
+    assert!(store.reload().is_ok());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[actix_web::test]
+async fn test_http_redirects_to_https_except_acme() {
+    let dir = std::env::temp_dir().join(format!("acme_test_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("token-123"), "respuesta-acme").unwrap();
+
+    let config = TlsConfig {
+        acme_challenge_dir: dir.to_string_lossy().into_owned(),
+        ..TlsConfig::default()
+    };
+    let listeners = [
+        ListenerConfig::default(),
+        ListenerConfig {
+            kind: ListenerKind::Https,
+            bind: "0.0.0.0:8443".to_string(),
+            ..ListenerConfig::default()
+        },
+    ];
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(HttpsRedirect::new(&config, &listeners)))
+            .route("/.well-known/acme-challenge/{token}", web::get().to(tls::acme_challenge))
+            .default_service(web::to(tls::https_redirect)),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/items?page=2")
+        .insert_header(("Host", "example.com:8080"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 301);
+    assert_eq!(resp.headers().get("location").unwrap(), "https://example.com:8443/items?page=2");
+
+    // X-Forwarded-Host solo cuenta si viene de un proxy de confianza
+    let req = test::TestRequest::get()
+        .uri("/")
+        .insert_header(("Host", "example.com"))
+        .insert_header(("X-Forwarded-Host", "evil.example"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.headers().get("location").unwrap(), "https://example.com:8443/");
+
+    let req = test::TestRequest::get().uri("/.well-known/acme-challenge/token-123").to_request();
+    assert_eq!(test::call_and_read_body(&app, req).await, "respuesta-acme");
+
+    let req = test::TestRequest::get().uri("/.well-known/acme-challenge/..%2Fsecreto").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 404);
+
+    fs::remove_dir_all(&dir).unwrap();
+}